@@ -2,11 +2,15 @@
 #![forbid(unsafe_code)]
 
 use error_iter::ErrorIter as _;
-use log::error;
+use log::{debug, error, info};
 use pixels::{Error, Pixels, SurfaceTexture};
-use rand::thread_rng;
-use tetris::random::RandomGenerator;
+use rand::{thread_rng, Rng};
+use tetris::input::{Button, InputFrame};
+use tetris::mode::GameMode;
+use tetris::random::{daily_seed, RandomGenerator, SeededRng};
 use tetris::rotate::SuperRotationSystem;
+use tetris::ruleset::Ruleset;
+use tetris::timing::FixedTimestep;
 use tetris::{CurrentPiece, Game};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, WindowEvent};
@@ -39,9 +43,33 @@ fn main() -> Result<(), Error> {
         Pixels::new(WIDTH * PIXEL_SIZE,HEIGHT * PIXEL_SIZE, surface_texture)?
     };
 
-    let rot = SuperRotationSystem{};
-    let rng = RandomGenerator::new(thread_rng());
-    let mut game = Game::new(rng, rot);
+    let rot = SuperRotationSystem::default();
+    let mode = match std::env::args().nth(1).as_deref() {
+        Some("sprint") => GameMode::sprint(),
+        Some("ultra") => GameMode::ultra(),
+        Some("marathon") => GameMode::marathon(),
+        Some("dig") => GameMode::dig(),
+        _ => GameMode::endless(),
+    };
+    // "daily" gives everyone the same pieces for the day
+    let seed = match std::env::args().nth(2).as_deref() {
+        Some("daily") => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            daily_seed((now.as_secs() / 86400) as u32)
+        }
+        Some(seed) => seed.parse().unwrap_or_else(|_| thread_rng().gen()),
+        None => thread_rng().gen(),
+    };
+    info!("Seed {seed}");
+    let ruleset = Ruleset::builder().mode(mode).build().unwrap();
+    let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(seed, rot, ruleset);
+    let mut timestep = FixedTimestep::new();
+    let mut last_update = std::time::Instant::now();
+    // Presses wait here until a tick has seen them, so a quick tap between ticks isn't lost
+    let mut pressed = InputFrame::empty();
+    let mut reported = false;
     {
         let frame = pixels.frame_mut();
         for l in frame.chunks_exact_mut(4) {
@@ -67,15 +95,17 @@ fn main() -> Result<(), Error> {
                 }
             }
 
-            let p = game.ghost_piece();
-            let mut c = p.color();
-            c.0 /= 2;
-            c.1 /= 2;
-            c.2 /= 2;
-            draw_mask(frame, 22, 7, p.y() + 2, p.mask(), c);
+            if let Some(p) = game.ghost_piece() {
+                let mut c = p.color();
+                c.0 /= 2;
+                c.1 /= 2;
+                c.2 /= 2;
+                draw_mask(frame, 22, 7, p.y() + 2, p.mask(), c);
+            }
 
-            let p = game.current_piece();
-            draw_mask(frame, 22, 7, p.y() + 2, p.mask(), p.color());
+            if let Some(p) = game.current_piece() {
+                draw_mask(frame, 22, 7, p.y() + 2, p.mask(), p.color());
+            }
 
             for x in 0..4 {
                 for y in 0..4 {
@@ -113,38 +143,20 @@ fn main() -> Result<(), Error> {
                 return;
             }
 
-            if input.key_pressed(KeyCode::KeyZ) {
-                game.rotate_left();
-            }
-
-            if input.key_pressed(KeyCode::KeyX) {
-                game.hold();
-            }
-
-            if input.key_pressed(KeyCode::ArrowUp) {
-                game.rotate_right();
-            }
-
-            if input.key_held(KeyCode::ArrowDown) {
-                game.set_drop(true);
-            } else if input.key_released(KeyCode::ArrowDown) {
-                game.set_drop(false);
-            }
-
-            if input.key_held(KeyCode::ArrowLeft) {
-                game.set_left(true);
-            } else if input.key_released(KeyCode::ArrowLeft) {
-                game.set_left(false);
-            }
-
-            if input.key_held(KeyCode::ArrowRight) {
-                game.set_right(true);
-            } else if input.key_released(KeyCode::ArrowRight) {
-                game.set_right(false);
+            if input.key_pressed(KeyCode::KeyP) {
+                if game.is_paused() {
+                    game.resume();
+                } else {
+                    game.pause();
+                }
             }
 
-            if input.key_pressed(KeyCode::Space) {
-                game.hard_drop();
+            // Start over with a new seed
+            if input.key_pressed(KeyCode::KeyR) {
+                let seed = thread_rng().gen();
+                info!("Seed {seed}");
+                game.reset(seed);
+                reported = false;
             }
 
             // Resize the window
@@ -156,14 +168,54 @@ fn main() -> Result<(), Error> {
                 }
             }
 
-            // Update internal state and request a redraw
-            game.update();
+            let mut frame = InputFrame::empty();
+            for (key, button) in [
+                (KeyCode::ArrowLeft, Button::Left),
+                (KeyCode::ArrowRight, Button::Right),
+                (KeyCode::ArrowDown, Button::SoftDrop),
+                (KeyCode::Space, Button::HardDrop),
+                (KeyCode::KeyZ, Button::RotateLeft),
+                (KeyCode::ArrowUp, Button::RotateRight),
+                (KeyCode::KeyA, Button::Rotate180),
+                (KeyCode::KeyX, Button::Hold),
+            ] {
+                if input.key_pressed(key) {
+                    pressed.set(button, true);
+                }
+                frame.set(button, input.key_held(key) || pressed.held(button));
+            }
+
+            // Run as many ticks as real time has passed, whatever the refresh rate
+            let now = std::time::Instant::now();
+            let ticks = timestep.advance(now - last_update);
+            for _ in 0..ticks {
+                game.tick(&frame);
+            }
+            if ticks > 0 {
+                pressed = InputFrame::empty();
+            }
+            last_update = now;
+            while let Some(event) = game.poll_event() {
+                debug!("{event:?}");
+            }
             window.request_redraw();
 
-            if !game.running() {
-                println!("done");
-                elwt.exit();
-                return;
+            if !game.running() && !reported {
+                reported = true;
+                println!("done, score: {}", game.score());
+                if let Some(reason) = game.game_over_reason() {
+                    println!("topped out: {reason:?}");
+                }
+                if let Some(result) = game.result() {
+                    println!(
+                        "{:?} in {:.2} s, {} pieces, {:.2} PPS",
+                        result.outcome,
+                        result.seconds(),
+                        result.pieces,
+                        result.pps()
+                    );
+                }
+                println!("press R to play again");
             }
         }
     });
@@ -11,14 +11,17 @@ use embassy_rp::clocks::{clk_sys_freq, RoscRng};
 use embassy_rp::gpio::{Input, Output};
 use embassy_rp::peripherals::PIO0;
 use embassy_rp::pio::{InterruptHandler, Pio};
-use embassy_rp::watchdog::Watchdog;
 use embassy_time::{Duration, Instant, Ticker, Timer};
 use fixed::types::U24F8;
 use log::info;
+use rand::RngCore;
 use smart_led_pio_sr::{PioWs2812SR, PioWs2812SRProgram};
 use smart_leds::RGB8;
-use tetris::random::RandomGenerator;
+use tetris::input::{self, InputFrame};
+use tetris::random::{RandomGenerator, SeededRng};
 use tetris::rotate::SuperRotationSystem;
+use tetris::ruleset::Ruleset;
+use tetris::timing::FixedTimestep;
 use tetris::{CurrentPiece, Game};
 use {defmt_rtt as _, panic_probe as _};
 
@@ -38,6 +41,12 @@ enum PinResult {
     Off,
 }
 
+impl PinResult {
+    fn held(&self) -> bool {
+        matches!(self, PinResult::RisingEdge | PinResult::On)
+    }
+}
+
 const DEBOUNCE: Duration = Duration::from_millis(30);
 
 struct Button<'d> {
@@ -101,9 +110,11 @@ async fn main(_spawner: Spawner) {
         &program,
     );
 
-    let rot = SuperRotationSystem{};
-    let rng = RandomGenerator::new(RoscRng);
-    let mut game = Game::new(rng, rot);
+    let rot = SuperRotationSystem::default();
+    // Log the seed so a game seen on the wall can be replayed
+    let seed = RoscRng.next_u64();
+    info!("Seed {}", seed);
+    let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(seed, rot, Ruleset::casual());
 
     let mut left_pin = Button::new(Input::new(p.PIN_15, embassy_rp::gpio::Pull::Up));
     let mut soft_drop_pin = Button::new(Input::new(p.PIN_14, embassy_rp::gpio::Pull::Up));
@@ -121,91 +132,97 @@ async fn main(_spawner: Spawner) {
         }
     }
     let mut ticker = Ticker::every(Duration::from_millis(16));
-    while game.running() {
-        for x in 0..10 {
-            for y in 0..20 {
-                draw_pixel(&mut data, x + 7, y + 2, game.board()[y as usize][x as usize]);
+    let mut timestep = FixedTimestep::new();
+    let mut last_update = Instant::now();
+    loop {
+        while game.running() {
+            for x in 0..10 {
+                for y in 0..20 {
+                    draw_pixel(&mut data, x + 7, y + 2, game.board()[y as usize][x as usize]);
+                }
             }
-        }
-
-        let p = game.ghost_piece();
-        let mut c = p.color();
-        c.0 /= 2;
-        c.1 /= 2;
-        c.2 /= 2;
-        draw_mask(&mut data, 22, 7, p.y() + 2, p.mask(), c);
 
-        let p = game.current_piece();
-        draw_mask(&mut data, 22, 7, p.y() + 2, p.mask(), p.color());
+            // Light up cleared rows while they wait to collapse
+            for y in game.clearing_rows().iter().filter(|y| **y < 20) {
+                for x in 0..10 {
+                    draw_pixel(&mut data, x + 7, y + 2, (255, 255, 255));
+                }
+            }
 
-        for x in 0..4 {
-            for y in 0..4 {
-                draw_pixel(&mut data, x + 1, 18 + y, (0, 0, 0));
+            if let Some(p) = game.ghost_piece() {
+                let mut c = p.color();
+                c.0 /= 2;
+                c.1 /= 2;
+                c.2 /= 2;
+                draw_mask(&mut data, 22, 7, p.y() + 2, p.mask(), c);
             }
-        }
 
-        if let Some(held) = game.held_piece() {
-            let p = CurrentPiece::new(held, 0, 0, tetris::Rotation::Rotate0);
-            draw_mask(&mut data, 24, 1, 18, p.mask(), p.color());
-        }
+            if let Some(p) = game.current_piece() {
+                draw_mask(&mut data, 22, 7, p.y() + 2, p.mask(), p.color());
+            }
 
-        for (i, piece) in game.next_pieces().iter().enumerate() {
-            let p = CurrentPiece::new(*piece, 0, 0, tetris::Rotation::Rotate0);
             for x in 0..4 {
-                for y in 0..2 {
-                    draw_pixel(&mut data, 19 + x, 20 - (3 * i as u32) + y, (0, 0, 0));
+                for y in 0..4 {
+                    draw_pixel(&mut data, x + 1, 18 + y, (0, 0, 0));
                 }
             }
-            draw_mask(&mut data, 24, 19, 20 - (3 * i as u32), p.mask(), p.color());
-        }
 
+            if let Some(held) = game.held_piece() {
+                let p = CurrentPiece::new(held, 0, 0, tetris::Rotation::Rotate0);
+                draw_mask(&mut data, 24, 1, 18, p.mask(), p.color());
+            }
 
-        match left_pin.get_state() {
-            PinResult::RisingEdge => game.set_left(true),
-            PinResult::FallingEdge => game.set_left(false),
-            _ => {},
-        }
+            for (i, piece) in game.next_pieces().iter().enumerate() {
+                let p = CurrentPiece::new(*piece, 0, 0, tetris::Rotation::Rotate0);
+                for x in 0..4 {
+                    for y in 0..2 {
+                        draw_pixel(&mut data, 19 + x, 20 - (3 * i as u32) + y, (0, 0, 0));
+                    }
+                }
+                draw_mask(&mut data, 24, 19, 20 - (3 * i as u32), p.mask(), p.color());
+            }
 
-        match right_pin.get_state() {
-            PinResult::RisingEdge => game.set_right(true),
-            PinResult::FallingEdge => game.set_right(false),
-            _ => {},
-        }
 
-        match soft_drop_pin.get_state() {
-            PinResult::RisingEdge => game.set_drop(true),
-            PinResult::FallingEdge => game.set_drop(false),
-            _ => {},
-        }
+            let mut frame = InputFrame::empty();
+            for (pin, button) in [
+                (&mut left_pin, input::Button::Left),
+                (&mut right_pin, input::Button::Right),
+                (&mut soft_drop_pin, input::Button::SoftDrop),
+                (&mut drop_pin, input::Button::HardDrop),
+                (&mut rotate_left_pin, input::Button::RotateLeft),
+                (&mut rotate_right_pin, input::Button::RotateRight),
+                (&mut hold_pin, input::Button::Hold),
+            ] {
+                frame.set(button, pin.get_state().held());
+            }
 
-        match hold_pin.get_state() {
-            PinResult::RisingEdge => game.hold(),
-            _ => {},
-        }
+            ws2812.write(&data).await;
 
-        match rotate_left_pin.get_state() {
-            PinResult::RisingEdge => game.rotate_left(),
-            _ => {},
-        }
+            // The ticker and LED writes drift from 60 Hz, so go by the clock
+            let now = Instant::now();
+            let elapsed = core::time::Duration::from_micros((now - last_update).as_micros());
+            for _ in 0..timestep.advance(elapsed) {
+                game.tick(&frame);
+            }
+            last_update = now;
 
-        match rotate_right_pin.get_state() {
-            PinResult::RisingEdge => game.rotate_right(),
-            _ => {},
+            ticker.next().await;
         }
 
-        match drop_pin.get_state() {
-            PinResult::RisingEdge => game.hard_drop(),
-            _ => {},
+        // Leave the final board up until hard drop is pressed and let go, then play again
+        while !matches!(drop_pin.get_state(), PinResult::RisingEdge) {
+            ticker.next().await;
+        }
+        while !matches!(drop_pin.get_state(), PinResult::FallingEdge) {
+            ticker.next().await;
         }
 
-        ws2812.write(&data).await;
-        game.update();
-
-        ticker.next().await;
+        let seed = RoscRng.next_u64();
+        info!("Seed {}", seed);
+        game.reset(seed);
+        timestep.reset();
+        last_update = Instant::now();
     }
-
-    let mut watchdog = Watchdog::new(p.WATCHDOG);
-    watchdog.trigger_reset();
 }
 
 
@@ -0,0 +1,144 @@
+use core::fmt;
+
+// Little endian reader and writer over plain byte slices, used for replays and snapshots
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    BufferTooSmall,
+    UnexpectedEnd,
+    BadMagic,
+    UnsupportedVersion(u8),
+    InvalidValue,
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::BufferTooSmall => write!(f, "buffer too small"),
+            CodecError::UnexpectedEnd => write!(f, "data ended unexpectedly"),
+            CodecError::BadMagic => write!(f, "not the expected kind of data"),
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported version {v}"),
+            CodecError::InvalidValue => write!(f, "invalid value"),
+        }
+    }
+}
+
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<(), CodecError> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(CodecError::BufferTooSmall);
+        }
+
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    // Overwrites already written bytes without moving the position
+    pub fn patch(&mut self, pos: usize, bytes: &[u8]) -> Result<(), CodecError> {
+        let end = pos + bytes.len();
+        if end > self.pos {
+            return Err(CodecError::BufferTooSmall);
+        }
+
+        self.buf[pos..end].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn u8(&mut self, v: u8) -> Result<(), CodecError> {
+        self.bytes(&[v])
+    }
+
+    pub fn bool(&mut self, v: bool) -> Result<(), CodecError> {
+        self.u8(v as u8)
+    }
+
+    pub fn u16(&mut self, v: u16) -> Result<(), CodecError> {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    pub fn u32(&mut self, v: u32) -> Result<(), CodecError> {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    pub fn u64(&mut self, v: u64) -> Result<(), CodecError> {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    pub fn f32(&mut self, v: f32) -> Result<(), CodecError> {
+        self.u32(v.to_bits())
+    }
+}
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+        let end = self.pos + N;
+        if end > self.buf.len() {
+            return Err(CodecError::UnexpectedEnd);
+        }
+
+        let mut out = [0; N];
+        out.copy_from_slice(&self.buf[self.pos..end]);
+        self.pos = end;
+        Ok(out)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, CodecError> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, CodecError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, CodecError> {
+        Ok(u16::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, CodecError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, CodecError> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, CodecError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+}
@@ -0,0 +1,97 @@
+use crate::mode::GameResult;
+use crate::score::{ClearKind, SpinKind};
+use crate::Piece;
+
+pub const EVENT_QUEUE_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverReason {
+    // A new piece spawned overlapping the stack
+    BlockOut,
+    // A piece locked entirely above the visible playfield
+    LockOut,
+    // A piece locked with some of its blocks above the visible playfield
+    PartialLockOut,
+    // Incoming garbage pushed blocks off the top of the playfield
+    GarbageOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEvent {
+    PieceSpawned { piece: Piece },
+    PieceRotated { piece: Piece, kick: usize },
+    PieceLocked { piece: Piece, spin: SpinKind },
+    // Rows are only valid up to `kind.lines`
+    LinesCleared { rows: [u32; 4], kind: ClearKind },
+    LevelUp { level: u32 },
+    Hold { held: Piece },
+    GarbageAdded { rows: u32 },
+    TopOut { reason: GameOverReason },
+    // Always the last event of a game, whether it was won or lost
+    Finished { result: GameResult },
+}
+
+// Fixed size ring buffer, the oldest event is dropped when it overflows
+#[derive(Debug, Clone)]
+pub struct EventQueue {
+    events: [Option<GameEvent>; EVENT_QUEUE_SIZE],
+    start: usize,
+    len: usize,
+    dropped: u32,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        EventQueue {
+            events: [None; EVENT_QUEUE_SIZE],
+            start: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        if self.len == EVENT_QUEUE_SIZE {
+            self.start = (self.start + 1) % EVENT_QUEUE_SIZE;
+            self.len -= 1;
+            self.dropped += 1;
+        }
+
+        self.events[(self.start + self.len) % EVENT_QUEUE_SIZE] = Some(event);
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<GameEvent> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let event = self.events[self.start].take();
+        self.start = (self.start + 1) % EVENT_QUEUE_SIZE;
+        self.len -= 1;
+        event
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Number of events lost because nobody drained the queue in time
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
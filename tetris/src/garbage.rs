@@ -0,0 +1,103 @@
+use rand::Rng;
+
+use crate::codec::{CodecError, Reader, Writer};
+use crate::random::SeededRng;
+
+pub const GARBAGE_COLOR: (u8, u8, u8) = (128, 128, 128);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GarbageHoles {
+    // Every row shares the same hole
+    #[default]
+    Clean,
+    // Every row gets a new random hole
+    Messy,
+    // Each row moves the hole with the given percent chance
+    Changes { percent: u8 },
+}
+
+impl GarbageHoles {
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        match self {
+            GarbageHoles::Clean => w.u8(0)?,
+            GarbageHoles::Messy => w.u8(1)?,
+            GarbageHoles::Changes { percent } => {
+                w.u8(2)?;
+                w.u8(*percent)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        match r.u8()? {
+            0 => Ok(GarbageHoles::Clean),
+            1 => Ok(GarbageHoles::Messy),
+            2 => Ok(GarbageHoles::Changes { percent: r.u8()? }),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GarbageGenerator {
+    rng: SeededRng,
+    hole: u32,
+    started: bool,
+}
+
+impl GarbageGenerator {
+    pub fn new(seed: u64) -> Self {
+        GarbageGenerator {
+            rng: SeededRng::new(seed),
+            hole: 0,
+            started: false,
+        }
+    }
+
+    fn random_hole(&mut self) -> u32 {
+        // Never pick the same column again so a change is always visible
+        if self.started {
+            (self.hole + self.rng.gen_range(1..10)) % 10
+        } else {
+            self.rng.gen_range(0..10)
+        }
+    }
+
+    pub fn next_hole(&mut self, holes: GarbageHoles) -> u32 {
+        let change = match holes {
+            GarbageHoles::Clean => !self.started,
+            GarbageHoles::Messy => true,
+            GarbageHoles::Changes { percent } => {
+                !self.started || self.rng.gen_range(0..100) < percent as u32
+            }
+        };
+
+        if change {
+            self.hole = self.random_hole();
+            self.started = true;
+        }
+
+        self.hole
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u64(self.rng.state())?;
+        w.u8(self.hole as u8)?;
+        w.bool(self.started)
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let rng = SeededRng::new(r.u64()?);
+        let hole = r.u8()? as u32;
+        if hole >= 10 {
+            return Err(CodecError::InvalidValue);
+        }
+
+        Ok(GarbageGenerator {
+            rng,
+            hole,
+            started: r.bool()?,
+        })
+    }
+}
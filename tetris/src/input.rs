@@ -0,0 +1,68 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Right,
+    SoftDrop,
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    Hold,
+    Rotate180,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::Left,
+        Button::Right,
+        Button::SoftDrop,
+        Button::HardDrop,
+        Button::RotateLeft,
+        Button::RotateRight,
+        Button::Hold,
+        Button::Rotate180,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+// Buttons held down during a single tick
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputFrame(u8);
+
+impl InputFrame {
+    pub const fn empty() -> Self {
+        InputFrame(0)
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        InputFrame(bits)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn held(&self, button: Button) -> bool {
+        self.0 & button.bit() != 0
+    }
+
+    pub fn set(&mut self, button: Button, held: bool) {
+        if held {
+            self.0 |= button.bit();
+        } else {
+            self.0 &= !button.bit();
+        }
+    }
+
+    pub fn with(mut self, button: Button, held: bool) -> Self {
+        self.set(button, held);
+        self
+    }
+
+    // Held now but not in the previous frame
+    pub fn pressed(&self, previous: &InputFrame, button: Button) -> bool {
+        self.held(button) && !previous.held(button)
+    }
+}
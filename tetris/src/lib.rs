@@ -1,14 +1,29 @@
 #![no_std]
 
-use micromath::F32Ext;
-
-use random::Randomizer;
-use rotate::Rotate;
+use event::{EventQueue, GameEvent, GameOverReason};
+use garbage::{GarbageGenerator, GARBAGE_COLOR};
+use input::{Button, InputFrame};
+use mode::{GameMode, GameResult, Outcome, Remaining};
+use random::{Randomizer, SeedableRandomizer};
+use rotate::{Rotate, SRS_SHAPES};
+use ruleset::{LockOutRule, Ruleset};
+use score::{ClearKind, SpinKind};
 
+pub mod codec;
+pub mod event;
+pub mod garbage;
+pub mod input;
+pub mod mode;
 pub mod random;
+pub mod replay;
 pub mod rotate;
+pub mod ruleset;
+pub mod score;
+pub mod snapshot;
+pub mod timing;
+pub mod versus;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Piece {
     I,
     J,
@@ -20,6 +35,20 @@ pub enum Piece {
 }
 
 impl Piece {
+    pub const ALL: [Piece; 7] = [
+        Piece::I,
+        Piece::J,
+        Piece::L,
+        Piece::O,
+        Piece::S,
+        Piece::T,
+        Piece::Z,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Piece> {
+        Piece::ALL.get(index as usize).copied()
+    }
+
     pub fn color(&self) -> (u8, u8, u8) {
         match &self {
             // I: Cyan
@@ -39,25 +68,13 @@ impl Piece {
         }
     }
 
+    // Spawns with the Super Rotation System's shapes
     pub fn spawn(self) -> CurrentPiece {
-        CurrentPiece {
-            piece: self,
-            x: match self {
-                Piece::I => 3,
-                Piece::J => 3,
-                Piece::L => 3,
-                Piece::O => 4,
-                Piece::S => 3,
-                Piece::T => 3,
-                Piece::Z => 3,
-            },
-            y: 20,
-            rotation: Rotation::Rotate0,
-        }
+        CurrentPiece::spawn(self, &SRS_SHAPES)
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Rotation {
     #[default]
     Rotate0,
@@ -67,6 +84,17 @@ pub enum Rotation {
 }
 
 impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::Rotate0,
+        Rotation::Rotate90,
+        Rotation::Rotate180,
+        Rotation::Rotate270,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Rotation> {
+        Rotation::ALL.get(index as usize).copied()
+    }
+
     pub fn right(&self) -> Rotation {
         match self {
             Rotation::Rotate0 => Rotation::Rotate90,
@@ -76,6 +104,15 @@ impl Rotation {
         }
     }
 
+    pub fn flip(&self) -> Rotation {
+        match self {
+            Rotation::Rotate0 => Rotation::Rotate180,
+            Rotation::Rotate90 => Rotation::Rotate270,
+            Rotation::Rotate180 => Rotation::Rotate0,
+            Rotation::Rotate270 => Rotation::Rotate90,
+        }
+    }
+
     pub fn left(&self) -> Rotation {
         match self {
             Rotation::Rotate0 => Rotation::Rotate270,
@@ -86,17 +123,52 @@ impl Rotation {
     }
 }
 
+// A piece in one rotation, placed inside the box it rotates in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    // From the bottom up, bit 0 is the leftmost column of the piece
+    pub rows: [u16; 4],
+    // Position of the rows' bottom left corner inside the rotation box
+    pub offset: (u32, u32),
+}
+
+impl Shape {
+    pub const fn new(rows: [u16; 4], x: u32, y: u32) -> Self {
+        Shape { rows, offset: (x, y) }
+    }
+}
+
+// Indexed by piece then rotation
+pub type ShapeTable = [[Shape; 4]; 7];
+
 #[derive(Debug, Clone)]
 pub struct CurrentPiece {
     piece: Piece,
     x: u32,
     y: u32,
     rotation: Rotation,
+    shapes: &'static ShapeTable,
 }
 
 impl CurrentPiece {
     pub fn new(piece: Piece, x: u32, y: u32, rotation: Rotation) -> Self {
-        CurrentPiece { piece, x, y, rotation }
+        CurrentPiece::with_shapes(piece, x, y, rotation, &SRS_SHAPES)
+    }
+
+    pub fn with_shapes(
+        piece: Piece,
+        x: u32,
+        y: u32,
+        rotation: Rotation,
+        shapes: &'static ShapeTable,
+    ) -> Self {
+        CurrentPiece { piece, x, y, rotation, shapes }
+    }
+
+    // Rotation boxes spawn at column 3 with the piece's bottom row at 20
+    pub fn spawn(piece: Piece, shapes: &'static ShapeTable) -> Self {
+        let shape = &shapes[piece as usize][Rotation::Rotate0 as usize];
+        CurrentPiece::with_shapes(piece, 3 + shape.offset.0, 20, Rotation::Rotate0, shapes)
     }
 
     pub fn x(&self) -> u32 {
@@ -107,72 +179,24 @@ impl CurrentPiece {
         self.y
     }
 
+    pub fn piece(&self) -> Piece {
+        self.piece
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    pub fn shapes(&self) -> &'static ShapeTable {
+        self.shapes
+    }
+
+    pub fn shape(&self) -> &'static Shape {
+        &self.shapes[self.piece as usize][self.rotation as usize]
+    }
+
     pub fn mask(&self) -> [u16; 4] {
-        match self.piece {
-            Piece::I => match self.rotation {
-                Rotation::Rotate0 | Rotation::Rotate180 => [0b1111 << self.x, 0, 0, 0],
-                Rotation::Rotate90 | Rotation::Rotate270 => {
-                    let piece_mask = 0b1 << self.x;
-                    [piece_mask, piece_mask, piece_mask, piece_mask]
-                }
-            },
-            Piece::J => match self.rotation {
-                Rotation::Rotate0 => [0b111 << self.x, 0b1 << self.x, 0, 0],
-                Rotation::Rotate90 => {
-                    let piece_mask = 0b1 << self.x;
-                    [piece_mask, piece_mask, 0b11 << self.x, 0]
-                }
-                Rotation::Rotate180 => [0b1 << self.x + 2, 0b111 << self.x, 0, 0],
-                Rotation::Rotate270 => {
-                    let piece_mask = 0b1 << self.x + 1;
-                    [0b11 << self.x, piece_mask, piece_mask, 0]
-                }
-            },
-            Piece::L => match self.rotation {
-                Rotation::Rotate0 => [0b111 << self.x, 0b1 << self.x + 2, 0, 0],
-                Rotation::Rotate90 => {
-                    let piece_mask = 0b1 << self.x;
-                    [0b11 << self.x, piece_mask, piece_mask, 0]
-                }
-                Rotation::Rotate180 => [0b1 << self.x, 0b111 << self.x, 0, 0],
-                Rotation::Rotate270 => {
-                    let piece_mask = 0b1 << self.x + 1;
-                    [piece_mask, piece_mask, 0b11 << self.x, 0]
-                }
-            },
-            Piece::O => {
-                let piece_mask = 0b11 << self.x;
-                [piece_mask, piece_mask, 0, 0]
-            }
-            Piece::S => match self.rotation {
-                Rotation::Rotate0 | Rotation::Rotate180 => {
-                    [0b11 << self.x, 0b11 << self.x + 1, 0, 0]
-                }
-                Rotation::Rotate90 | Rotation::Rotate270 => {
-                    [0b1 << self.x + 1, 0b11 << self.x, 0b1 << self.x, 0]
-                }
-            },
-            Piece::T => match self.rotation {
-                Rotation::Rotate0 => [0b111 << self.x, 0b1 << self.x + 1, 0, 0],
-                Rotation::Rotate90 => {
-                    let piece_mask = 0b1 << self.x;
-                    [piece_mask, 0b11 << self.x, piece_mask, 0]
-                }
-                Rotation::Rotate180 => [0b1 << self.x + 1, 0b111 << self.x, 0, 0],
-                Rotation::Rotate270 => {
-                    let piece_mask = 0b1 << self.x + 1;
-                    [piece_mask, 0b11 << self.x, piece_mask, 0]
-                }
-            },
-            Piece::Z => match self.rotation {
-                Rotation::Rotate0 | Rotation::Rotate180 => {
-                    [0b11 << self.x + 1, 0b11 << self.x, 0, 0]
-                }
-                Rotation::Rotate90 | Rotation::Rotate270 => {
-                    [0b1 << self.x, 0b11 << self.x, 0b1 << self.x + 1, 0]
-                }
-            },
-        }
+        self.shape().rows.map(|row| row << self.x)
     }
 
     pub fn color(&self) -> (u8, u8, u8) {
@@ -196,16 +220,14 @@ impl CurrentPiece {
 
 pub type PlayfieldMask = [u16; 40];
 
-#[derive(Debug, Clone)]
-pub struct Ruleset {
-    das_delay: u32,
-    das_gravity: f32,
-    drop_gravity_multipler: f32,
-    lock_delay: u32,
-    lock_resets: u32,
-    line_clear_constant: u32,
-    line_clear_coeff: u32,
-}
+// Length of the next queue, rulesets can show fewer
+pub const MAX_PREVIEWS: usize = 7;
+
+// Every timer in the engine counts calls to `Game::update`, `timing` maps them to real time
+pub const TICKS_PER_SECOND: u32 = 60;
+
+// Gravity in rows per tick at which pieces drop straight to the floor
+pub const TWENTY_G: f32 = 20.0;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum HeldDirection {
@@ -222,9 +244,10 @@ where
     ROT: Rotate,
 {
     game_ended: bool,
+    paused: bool,
     current_piece: CurrentPiece,
     ghost_piece: CurrentPiece,
-    next_pieces: [Piece; 6],
+    next_pieces: [Piece; MAX_PREVIEWS],
     playfield_mask: PlayfieldMask,
     playfield_colors: [[(u8, u8, u8); 10]; 40],
     randomizer: RNG,
@@ -239,51 +262,82 @@ where
     movement: f32,
     lock_ticks: u32,
     lock_tries: u32,
-    das_movement: f32,
     das_ticks: u32,
+    // Ticks since the last auto shift
+    arr_ticks: u32,
+    // Ticks auto shift stays paused after a rotation, hold or spawn
+    das_cut_ticks: u32,
     left_held: bool,
     right_held: bool,
     held_direction: HeldDirection,
     drop_held: bool,
+    // Buttons held during the last call to `tick`
+    input: InputFrame,
     line_clears: ([u32; 4], usize),
+    // Ticks left before cleared rows collapse
+    line_clear_ticks: u32,
+    // Ticks left before the next piece spawns, None while a piece is in play
+    spawn_ticks: Option<u32>,
+    // Tick the last piece locked on, the delays after it start counting on the next one
+    lock_tick: u32,
+    // Rotation and hold pressed during ARE, applied when the next piece spawns
+    buffered_rotation: Rotation,
+    buffered_hold: bool,
+    score: u32,
+    combo: Option<u32>,
+    back_to_back: bool,
+    last_clear: Option<ClearKind>,
+    // Turn and kick used by the last rotation, cleared by any other successful movement
+    last_rotation: Option<(Rotation, usize)>,
+    events: EventQueue,
+    ticks: u32,
+    pieces_placed: u32,
+    result: Option<GameResult>,
+    garbage: GarbageGenerator,
+    garbage_cleared: u32,
+    seed: Option<u64>,
+}
+
+impl<RNG: SeedableRandomizer, ROT: Rotate> Game<RNG, ROT> {
+    // Pieces and garbage both follow the seed
+    pub fn from_seed(seed: u64, rot: ROT, ruleset: Ruleset) -> Game<RNG, ROT> {
+        let mut g = Game::with_ruleset(RNG::from_seed(seed), rot, ruleset);
+        g.set_garbage_seed(seed);
+        g.seed = Some(seed);
+        g
+    }
+
+    // Starts a new game in place with the same rules and rotation system
+    pub fn reset(&mut self, seed: u64)
+    where
+        ROT: Clone,
+    {
+        // Buttons still held from the last game shouldn't count as presses in the new one
+        let input = self.input;
+        *self = Game::from_seed(seed, self.rotation.clone(), self.ruleset.clone());
+        self.input = input;
+    }
 }
 
 impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
-    pub fn new(mut rng: RNG, rot: ROT) -> Game<RNG, ROT> {
-        let piece = rng.get_next_piece().spawn();
+    pub fn new(rng: RNG, rot: ROT) -> Game<RNG, ROT> {
+        Game::with_ruleset(rng, rot, Ruleset::default())
+    }
+
+    pub fn with_ruleset(mut rng: RNG, rot: ROT, ruleset: Ruleset) -> Game<RNG, ROT> {
+        let piece = CurrentPiece::spawn(rng.get_next_piece(), rot.shapes());
         let mut g = Game {
             game_ended: false,
+            paused: false,
             current_piece: piece.clone(),
             ghost_piece: piece,
-            next_pieces: [
-                rng.get_next_piece(),
-                rng.get_next_piece(),
-                rng.get_next_piece(),
-                rng.get_next_piece(),
-                rng.get_next_piece(),
-                rng.get_next_piece(),
-            ],
+            next_pieces: core::array::from_fn(|_| rng.get_next_piece()),
             // Make it so outside the playfeild x >= 10 is masked as something there
             playfield_mask: [0b1111110000000000; 40],
             playfield_colors: [[(0, 0, 0); 10]; 40],
             randomizer: rng,
             rotation: rot,
-            ruleset: Ruleset {
-                // 300 ms (18 ticks / 60 fps = 3/10 s)
-                das_delay: 18,
-                // 1 Tile / 2 Tick
-                das_gravity: 0.5,
-                // 20x Normal Drop Speed
-                drop_gravity_multipler: 20.0,
-                // 1 s (60 ticks / 60 fps = 1 s)
-                lock_delay: 60,
-                // 25 Moves to reset lock delay
-                lock_resets: 25,
-                // 10 / 0 for fixed and 5 / 5 for variable
-                // line_clear_constant: 10,
-                line_clear_constant: 10,
-                line_clear_coeff: 0,
-            },
+            ruleset,
             held_piece: None,
             hold_lock: false,
             gravity: 0.0,
@@ -297,13 +351,36 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
             left_held: false,
             right_held: false,
             drop_held: false,
-            das_movement: 0.0,
+            input: InputFrame::empty(),
             das_ticks: 0,
+            arr_ticks: 0,
+            das_cut_ticks: 0,
             line_clears: ([0, 0, 0, 0], 0),
+            line_clear_ticks: 0,
+            spawn_ticks: None,
+            lock_tick: 0,
+            buffered_rotation: Rotation::Rotate0,
+            buffered_hold: false,
+            score: 0,
+            combo: None,
+            back_to_back: false,
+            last_clear: None,
+            last_rotation: None,
+            events: EventQueue::new(),
+            ticks: 0,
+            pieces_placed: 0,
+            result: None,
+            garbage: GarbageGenerator::new(0),
+            garbage_cleared: 0,
+            seed: None,
         };
 
         g.update_ghost();
         g.update_gravity();
+        g.events.push(GameEvent::PieceSpawned {
+            piece: g.current_piece.piece,
+        });
+        g.start_dig();
 
         g
     }
@@ -312,28 +389,237 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
         !self.game_ended
     }
 
-    pub fn current_piece(&self) -> &CurrentPiece {
-        &self.current_piece
+    // Timers stop and input is ignored until `resume`
+    pub fn pause(&mut self) {
+        if !self.game_ended {
+            self.paused = true;
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
     }
 
-    pub fn ghost_piece(&self) -> &CurrentPiece {
-        &self.ghost_piece
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // None between a piece locking and the next one spawning
+    pub fn current_piece(&self) -> Option<&CurrentPiece> {
+        if self.piece_active() {
+            Some(&self.current_piece)
+        } else {
+            None
+        }
+    }
+
+    // None when the ruleset hides the ghost or there is no piece in play
+    pub fn ghost_piece(&self) -> Option<&CurrentPiece> {
+        if self.ruleset.ghost && self.piece_active() {
+            Some(&self.ghost_piece)
+        } else {
+            None
+        }
+    }
+
+    pub fn piece_active(&self) -> bool {
+        self.spawn_ticks.is_none()
+    }
+
+    // Full rows still on the board during the line clear delay
+    pub fn clearing_rows(&self) -> &[u32] {
+        &self.line_clears.0[..self.line_clears.1]
     }
 
     pub fn held_piece(&self) -> Option<Piece> {
         self.held_piece
     }
 
-    pub fn next_pieces(&self) -> [Piece; 6] {
-        self.next_pieces
+    // Only as many as the ruleset shows
+    pub fn next_pieces(&self) -> &[Piece] {
+        &self.next_pieces[..self.ruleset.previews]
+    }
+
+    // Fills `pieces` with the upcoming pieces, looking past the next queue if needed
+    pub fn peek(&self, pieces: &mut [Piece])
+    where
+        RNG: Clone,
+    {
+        let queued = pieces.len().min(MAX_PREVIEWS);
+        pieces[..queued].copy_from_slice(&self.next_pieces[..queued]);
+        self.randomizer.peek(&mut pieces[queued..]);
     }
 
     pub fn board(&self) -> &[[(u8, u8, u8); 10]; 40] {
         &self.playfield_colors
     }
 
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.ruleset.mode
+    }
+
+    // Lines or ticks left until the mode's goal, if it has one
+    pub fn remaining(&self) -> Option<Remaining> {
+        self.ruleset
+            .mode
+            .remaining(self.line_clear_total, self.garbage_cleared, self.ticks)
+    }
+
+    // Set for games made with `from_seed`, which can be played again with the same seed
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn garbage_cleared(&self) -> u32 {
+        self.garbage_cleared
+    }
+
+    // Garbage holes are deterministic, set this before the first update to vary them
+    pub fn set_garbage_seed(&mut self, seed: u64) {
+        self.garbage = GarbageGenerator::new(seed);
+        if self.ticks == 0 && self.pieces_placed == 0 && self.ruleset.mode.garbage_goal().is_some() {
+            for y in 0..40 {
+                self.playfield_mask[y] = 0b1111110000000000;
+                self.playfield_colors[y] = [(0, 0, 0); 10];
+            }
+            self.start_dig();
+        }
+    }
+
+    fn start_dig(&mut self) {
+        if let Some(rows) = self.ruleset.mode.garbage_goal() {
+            // The starting garbage is part of the board, not something that happened
+            self.push_garbage(rows);
+            self.events.clear();
+            self.events.push(GameEvent::PieceSpawned {
+                piece: self.current_piece.piece,
+            });
+        }
+    }
+
+    // Pushes rows of garbage in from the bottom, with holes picked by the ruleset
+    pub fn push_garbage(&mut self, rows: u32) {
+        for _ in 0..rows {
+            let hole = self.garbage.next_hole(self.ruleset.garbage);
+            self.push_garbage_with_hole(1, hole);
+        }
+    }
+
+    pub fn push_garbage_with_hole(&mut self, rows: u32, hole: u32) {
+        let rows = (rows as usize).min(40);
+        if rows == 0 || self.game_ended {
+            return;
+        }
+
+        let overflow = self.playfield_mask[40 - rows..]
+            .iter()
+            .any(|l| *l != 0b1111110000000000);
+
+        for y in (rows..40).rev() {
+            self.playfield_mask[y] = self.playfield_mask[y - rows];
+            self.playfield_colors[y] = self.playfield_colors[y - rows];
+        }
+
+        for y in 0..rows {
+            self.playfield_mask[y] = !(1 << hole.min(9));
+            for x in 0..10 {
+                self.playfield_colors[y][x] = if x as u32 == hole.min(9) {
+                    (0, 0, 0)
+                } else {
+                    GARBAGE_COLOR
+                };
+            }
+        }
+
+        // Rows waiting to be cleared moved up with everything else
+        for i in 0..self.line_clears.1 {
+            self.line_clears.0[i] += rows as u32;
+        }
+
+        self.events.push(GameEvent::GarbageAdded { rows: rows as u32 });
+
+        if overflow {
+            self.top_out(GameOverReason::GarbageOut);
+            return;
+        }
+
+        if !self.piece_active() {
+            return;
+        }
+
+        // Push the active piece up out of the way if the new rows reached it
+        for _ in 0..rows {
+            if !self.current_piece.collides(&self.playfield_mask) {
+                break;
+            }
+            self.current_piece.y += 1;
+        }
+
+        if self.current_piece.collides(&self.playfield_mask) {
+            self.top_out(GameOverReason::GarbageOut);
+        }
+
+        self.update_ghost();
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    // Why the game ended, None while it runs or if the mode's goal was reached
+    pub fn game_over_reason(&self) -> Option<GameOverReason> {
+        match self.result?.outcome {
+            Outcome::ToppedOut(reason) => Some(reason),
+            Outcome::Completed => None,
+        }
+    }
+
+    pub fn elapsed_ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    pub fn pieces_placed(&self) -> u32 {
+        self.pieces_placed
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.line_clear_total
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    pub fn last_clear(&self) -> Option<ClearKind> {
+        self.last_clear
+    }
+
+    pub fn poll_event(&mut self) -> Option<GameEvent> {
+        self.events.pop()
+    }
+
+    pub fn events(&self) -> &EventQueue {
+        &self.events
+    }
+
     fn update_gravity(&mut self) {
-        self.gravity = 1.0 / (((0.8 - ((self.level as f32) * 0.007)).powi(self.level as i32)) * 60.0);
+        self.gravity = self.ruleset.gravity_curve.gravity(self.level);
     }
 
     fn update_ghost(&mut self) {
@@ -347,11 +633,46 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
         }
     }
 
+    fn finish(&mut self, outcome: Outcome) {
+        if self.game_ended {
+            return;
+        }
+
+        self.game_ended = true;
+        if let Outcome::ToppedOut(reason) = outcome {
+            self.events.push(GameEvent::TopOut { reason });
+        }
+
+        let result = GameResult {
+            outcome,
+            ticks: self.ticks,
+            pieces: self.pieces_placed,
+            lines: self.line_clear_total,
+            score: self.score,
+        };
+        self.result = Some(result);
+        self.events.push(GameEvent::Finished { result });
+    }
+
+    fn top_out(&mut self, reason: GameOverReason) {
+        self.finish(Outcome::ToppedOut(reason));
+    }
+
     fn lock_piece(&mut self, piece: &CurrentPiece) {
-        if piece.y > 20 {
-            self.game_ended = true;
+        if self.game_ended {
+            return;
         }
 
+        self.pieces_placed += 1;
+        self.lock_tick = self.ticks;
+        let spin = self.detect_spin(piece);
+        self.events.push(GameEvent::PieceLocked {
+            piece: piece.piece,
+            spin,
+        });
+
+        let lock_out = self.lock_out(piece);
+
         let c = piece.color();
         for (i, m) in piece.mask().iter().enumerate() {
             let y = piece.y as usize + i;
@@ -365,9 +686,251 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
             }
         }
 
-        let piece = self.get_next_piece();
-        self.new_piece(piece);
+        self.check_line_clears();
+        self.score_lock(spin);
+        self.count_lines();
+
+        // Scored first so the result counts this lock and nothing comes after `Finished`
+        if let Some(reason) = lock_out {
+            self.top_out(reason);
+            return;
+        }
+
+        if let Some(goal) = self.ruleset.mode.line_goal() {
+            if self.line_clear_total >= goal {
+                self.finish(Outcome::Completed);
+                return;
+            }
+        }
+
+        if let Some(goal) = self.ruleset.mode.garbage_goal() {
+            if self.garbage_cleared >= goal {
+                self.finish(Outcome::Completed);
+                return;
+            }
+        }
+
+        // Leave the last piece where it locked
+        if self.game_ended {
+            return;
+        }
+
+        if self.line_clears.1 > 0 {
+            self.spawn_ticks = Some(self.ruleset.line_are);
+            if self.ruleset.line_clear_delay == 0 {
+                self.collapse_lines();
+            } else {
+                self.line_clear_ticks = self.ruleset.line_clear_delay;
+                return;
+            }
+        } else {
+            self.spawn_ticks = Some(self.ruleset.are);
+        }
+
+        if self.spawn_ticks == Some(0) {
+            self.spawn_next();
+        }
+    }
+
+    fn lock_out(&self, piece: &CurrentPiece) -> Option<GameOverReason> {
+        let mask = piece.mask();
+        let rows = (0..4).filter(|i| mask[*i] != 0).map(|i| piece.y + i as u32);
+        let (lowest, highest) = (rows.clone().min()?, rows.max()?);
+
+        match self.ruleset.lock_out {
+            LockOutRule::Guideline | LockOutRule::Partial if lowest >= 20 => {
+                Some(GameOverReason::LockOut)
+            }
+            LockOutRule::Partial if highest >= 20 => Some(GameOverReason::PartialLockOut),
+            _ => None,
+        }
+    }
+
+    fn spawn_next(&mut self) {
+        self.spawn_ticks = None;
         self.hold_lock = false;
+        if !self.ruleset.preserve_das {
+            self.das_ticks = 0;
+        }
+
+        let mut piece = self.get_next_piece();
+        if self.buffered_hold {
+            self.buffered_hold = false;
+            piece = self.swap_hold(piece);
+            self.hold_lock = true;
+        }
+        self.new_piece(piece);
+    }
+
+    fn collapse_lines(&mut self) {
+        for i in (0..self.line_clears.1).rev() {
+            let l = self.line_clears.0[i] as usize;
+            for i in l..39 {
+                self.playfield_mask[i] = self.playfield_mask[i+1];
+                for c in 0..10 {
+                    self.playfield_colors[i][c] = self.playfield_colors[i+1][c];
+                }
+            }
+
+            self.playfield_mask[39] = 0b1111110000000000;
+            for c in 0..10 {
+                self.playfield_colors[39][c] = (0, 0, 0);
+            }
+        }
+
+        self.line_clears.1 = 0;
+        self.line_clear_ticks = 0;
+        self.update_ghost();
+    }
+
+    fn occupied(&self, x: i32, y: i32) -> bool {
+        if !(0..10).contains(&x) || !(0..40).contains(&y) {
+            true
+        } else {
+            self.playfield_mask[y as usize] & (1 << x) != 0
+        }
+    }
+
+    // 3-corner rule, must run before the piece is written to the playfield
+    fn detect_spin(&self, piece: &CurrentPiece) -> SpinKind {
+        let (turn, kick) = match self.last_rotation {
+            Some(last) if piece.piece == Piece::T => last,
+            _ => return SpinKind::None,
+        };
+
+        let mask = piece.mask();
+        let filled = |x: i32, y: i32| {
+            let row = y - piece.y as i32;
+            (0..4).contains(&row) && (0..16).contains(&x) && mask[row as usize] & (1 << x) != 0
+        };
+
+        // The center of the T is the only block with three neighbours
+        let mut center = None;
+        for (i, m) in mask.iter().enumerate() {
+            for x in 0..10 {
+                if m & (1 << x) != 0 {
+                    let y = piece.y as i32 + i as i32;
+                    let neighbours = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                        .iter()
+                        .filter(|(dx, dy)| filled(x + dx, y + dy))
+                        .count();
+                    if neighbours == 3 {
+                        center = Some((x, y));
+                    }
+                }
+            }
+        }
+
+        let (cx, cy) = match center {
+            Some(c) => c,
+            None => return SpinKind::None,
+        };
+
+        // The T points away from the side without a neighbour
+        let (fx, fy) = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .find(|(dx, dy)| !filled(cx + dx, cy + dy))
+            .map(|(dx, dy)| (-dx, -dy))
+            .unwrap_or((0, 1));
+
+        let mut corners = 0;
+        let mut front = 0;
+        for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+            if self.occupied(cx + dx, cy + dy) {
+                corners += 1;
+                if dx == fx || dy == fy {
+                    front += 1;
+                }
+            }
+        }
+
+        if corners < 3 {
+            SpinKind::None
+        } else if front == 2 || (kick == 4 && turn != Rotation::Rotate180) {
+            // The last SRS kick of a quarter turn always counts as a full T-spin
+            SpinKind::Full
+        } else {
+            SpinKind::Mini
+        }
+    }
+
+    fn count_lines(&mut self) {
+        if self.line_clears.1 == 0 {
+            return;
+        }
+
+        self.line_clear_count += self.line_clears.1 as u32;
+        self.line_clear_total += self.line_clears.1 as u32;
+        let limit = self.ruleset.line_clear_constant + (self.level * self.ruleset.line_clear_coeff);
+        if self.line_clear_count >= limit {
+            self.line_clear_count = 0;
+            self.level += 1;
+            self.update_gravity();
+            self.events.push(GameEvent::LevelUp { level: self.level });
+        }
+    }
+
+    fn check_line_clears(&mut self) {
+        for (i, l) in self.playfield_mask.iter().enumerate() {
+            if !(*l) == 0 {
+                // Full line
+                self.line_clears.0[self.line_clears.1] = i as u32;
+                self.line_clears.1 += 1;
+                if self.playfield_colors[i].contains(&GARBAGE_COLOR) {
+                    self.garbage_cleared += 1;
+                }
+
+                if self.line_clears.1 >= 4 {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn score_lock(&mut self, spin: SpinKind) {
+        let lines = self.line_clears.1 as u32;
+        if lines == 0 {
+            self.combo = None;
+            if spin != SpinKind::None {
+                let clear = ClearKind {
+                    lines,
+                    spin,
+                    combo: 0,
+                    back_to_back: false,
+                    perfect_clear: false,
+                };
+                self.score += self.ruleset.scoring.clear_points(&clear) * (self.level + 1);
+                self.last_clear = Some(clear);
+            }
+            return;
+        }
+
+        let combo = self.combo.map_or(0, |c| c + 1);
+        self.combo = Some(combo);
+
+        // Every row is either about to be cleared or empty
+        let perfect_clear = self
+            .playfield_mask
+            .iter()
+            .all(|l| !(*l) == 0 || *l == 0b1111110000000000);
+
+        let mut clear = ClearKind {
+            lines,
+            spin,
+            combo,
+            back_to_back: false,
+            perfect_clear,
+        };
+        let difficult = clear.difficult();
+        clear.back_to_back = difficult && self.back_to_back;
+        self.back_to_back = difficult;
+
+        self.score += self.ruleset.scoring.clear_points(&clear) * (self.level + 1);
+        self.last_clear = Some(clear);
+        self.events.push(GameEvent::LinesCleared {
+            rows: self.line_clears.0,
+            kind: clear,
+        });
     }
 
     fn get_next_piece(&mut self) -> Piece {
@@ -382,15 +945,82 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
     }
 
     fn new_piece(&mut self, piece: Piece) {
-        self.current_piece = piece.spawn();
+        self.current_piece = CurrentPiece::spawn(piece, self.rotation.shapes());
+        self.events.push(GameEvent::PieceSpawned { piece });
+        self.initial_rotation();
+        // Checked after the initial rotation, which can save a piece that would spawn in the stack
         if self.current_piece.collides(&self.playfield_mask) {
-            // Lock out
-            self.game_ended = true;
+            self.top_out(GameOverReason::BlockOut);
         }
         self.movement = 0.0;
         self.lock_ticks = 0;
         self.lock_tries = 0;
+        self.last_rotation = None;
+        self.arr_ticks = 0;
+        self.das_cut_ticks = self.ruleset.das_cut_delay;
         self.update_ghost();
+        self.apply_20g();
+    }
+
+    fn initial_rotation(&mut self) {
+        let rotation = core::mem::take(&mut self.buffered_rotation);
+        let rotated = match rotation {
+            Rotation::Rotate0 => return,
+            Rotation::Rotate90 => self
+                .rotation
+                .rotate_right(&self.current_piece, &self.playfield_mask),
+            Rotation::Rotate180 => self
+                .rotation
+                .rotate_180(&self.current_piece, &self.playfield_mask),
+            Rotation::Rotate270 => self
+                .rotation
+                .rotate_left(&self.current_piece, &self.playfield_mask),
+        };
+
+        // The piece spawns unrotated if there is no room to turn it
+        if let Some((rot, kick)) = rotated {
+            self.current_piece = rot;
+            self.events.push(GameEvent::PieceRotated {
+                piece: self.current_piece.piece,
+                kick,
+            });
+        }
+    }
+
+    // Buffers a rotation during ARE, returns true if the piece can't be rotated now
+    fn buffer_rotation(&mut self, turn: fn(&Rotation) -> Rotation) -> bool {
+        if self.paused || self.game_ended {
+            return true;
+        }
+
+        if self.piece_active() {
+            return false;
+        }
+
+        if self.ruleset.initial_rotation {
+            self.buffered_rotation = turn(&self.buffered_rotation);
+        }
+        true
+    }
+
+    fn drop_to_ghost(&mut self) {
+        // The ghost sits above the piece when it spawned into the stack
+        let rows = self.current_piece.y.saturating_sub(self.ghost_piece.y);
+        if rows > 0 {
+            self.current_piece.y = self.ghost_piece.y;
+            self.lock_ticks = 0;
+            self.last_rotation = None;
+            if self.drop_held {
+                self.score += rows * self.ruleset.scoring.soft_drop;
+            }
+        }
+    }
+
+    // At 20G a piece never hangs in the air, not even right after it spawns or moves
+    fn apply_20g(&mut self) {
+        if self.gravity >= TWENTY_G && !self.game_ended {
+            self.drop_to_ghost();
+        }
     }
 
     fn reset_lock(&mut self) {
@@ -400,73 +1030,158 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
         }
     }
 
+    // Puts a piece in hold and returns the one to play instead
+    fn swap_hold(&mut self, piece: Piece) -> Piece {
+        let new_piece = if let Some(held) = self.held_piece {
+            held
+        } else {
+            self.get_next_piece()
+        };
+
+        self.held_piece = Some(piece);
+        self.events.push(GameEvent::Hold { held: piece });
+        new_piece
+    }
+
     pub fn hold(&mut self) {
-        if !self.hold_lock {
-            let new_piece = if let Some(held) = self.held_piece {
-                held
-            } else {
-                self.get_next_piece()
-            };
+        if !self.ruleset.hold || self.paused || self.game_ended {
+            return;
+        }
 
-            self.held_piece = Some(self.current_piece.piece);
-            
-            self.update_ghost();
+        if !self.piece_active() {
+            if self.ruleset.initial_hold {
+                self.buffered_hold = true;
+            }
+            return;
+        }
+
+        if !self.hold_lock {
+            let new_piece = self.swap_hold(self.current_piece.piece);
             self.new_piece(new_piece);
             self.hold_lock = true;
         }
     }
 
     pub fn move_left(&mut self) {
-        if self.current_piece.x > 0 {
+        if self.current_piece.x > 0 && self.piece_active() && !self.paused && !self.game_ended {
             self.current_piece.x -= 1;
             if self.current_piece.collides(&self.playfield_mask) {
                 self.current_piece.x += 1;
             } else {
+                self.last_rotation = None;
                 self.reset_lock();
                 self.update_ghost();
+                self.apply_20g();
             }
         }
     }
 
     pub fn move_right(&mut self) {
-        if self.current_piece.x < 10 {
+        if self.current_piece.x < 10 && self.piece_active() && !self.paused && !self.game_ended {
             self.current_piece.x += 1;
             if self.current_piece.collides(&self.playfield_mask) {
                 self.current_piece.x -= 1;
             } else {
+                self.last_rotation = None;
                 self.reset_lock();
                 self.update_ghost();
+                self.apply_20g();
             }
         }
     }
 
     pub fn rotate_left(&mut self) {
-        if let Some(rot) = self
+        if self.buffer_rotation(Rotation::left) {
+            return;
+        }
+
+        if let Some((rot, kick)) = self
             .rotation
             .rotate_left(&self.current_piece, &self.playfield_mask)
         {
             self.current_piece = rot;
+            self.last_rotation = Some((Rotation::Rotate270, kick));
+            self.events.push(GameEvent::PieceRotated {
+                piece: self.current_piece.piece,
+                kick,
+            });
+            self.das_cut_ticks = self.ruleset.das_cut_delay;
             self.reset_lock();
             self.update_ghost();
+            self.apply_20g();
         }
     }
 
     pub fn rotate_right(&mut self) {
-        if let Some(rot) = self
+        if self.buffer_rotation(Rotation::right) {
+            return;
+        }
+
+        if let Some((rot, kick)) = self
             .rotation
             .rotate_right(&self.current_piece, &self.playfield_mask)
         {
             self.current_piece = rot;
+            self.last_rotation = Some((Rotation::Rotate90, kick));
+            self.events.push(GameEvent::PieceRotated {
+                piece: self.current_piece.piece,
+                kick,
+            });
+            self.das_cut_ticks = self.ruleset.das_cut_delay;
             self.reset_lock();
             self.update_ghost();
+            self.apply_20g();
+        }
+    }
+
+    // Does nothing if the rotation system has no 180 rotations
+    pub fn rotate_180(&mut self) {
+        if self.buffer_rotation(Rotation::flip) {
+            return;
+        }
+
+        if let Some((rot, kick)) = self
+            .rotation
+            .rotate_180(&self.current_piece, &self.playfield_mask)
+        {
+            self.current_piece = rot;
+            self.last_rotation = Some((Rotation::Rotate180, kick));
+            self.events.push(GameEvent::PieceRotated {
+                piece: self.current_piece.piece,
+                kick,
+            });
+            self.das_cut_ticks = self.ruleset.das_cut_delay;
+            self.reset_lock();
+            self.update_ghost();
+            self.apply_20g();
         }
     }
 
     pub fn hard_drop(&mut self) {
+        if !self.ruleset.hard_drop || !self.piece_active() || self.paused || self.game_ended {
+            return;
+        }
+
         let ghost = self.ghost_piece.clone();
+        if ghost.y < self.current_piece.y {
+            // Falling is a movement, so a rotation before it doesn't make a spin
+            self.last_rotation = None;
+        }
+        self.score += self.current_piece.y.saturating_sub(ghost.y) * self.ruleset.scoring.hard_drop;
         self.lock_piece(&ghost);
     }
 
+    // Moves the piece towards the held direction, returns false if it couldn't move
+    fn auto_shift(&mut self) -> bool {
+        let x = self.current_piece.x;
+        match self.held_direction {
+            HeldDirection::None => {}
+            HeldDirection::Left => self.move_left(),
+            HeldDirection::Right => self.move_right(),
+        }
+        self.current_piece.x != x
+    }
+
     pub fn set_drop(&mut self, state: bool) {
         self.drop_held = state;
     }
@@ -477,7 +1192,8 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
                 self.move_left();
             }
             self.das_ticks = 0;
-            self.das_movement = 0.0;
+            self.arr_ticks = 0;
+            self.das_cut_ticks = 0;
             self.left_held = state;
             self.held_direction = if state {
                 HeldDirection::Left
@@ -497,7 +1213,8 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
                 self.move_right();
             }
             self.das_ticks = 0;
-            self.das_movement = 0.0;
+            self.arr_ticks = 0;
+            self.das_cut_ticks = 0;
             self.right_held = state;
             self.held_direction = if state {
                 HeldDirection::Right
@@ -511,69 +1228,164 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
         }
     }
 
+    // Runs one tick with the buttons held during it. Presses are found by comparing with
+    // the previous frame and applied in a fixed order, so the same frames always give the
+    // same game no matter what order the frontend read its buttons in.
+    pub fn tick(&mut self, frame: &InputFrame) {
+        let previous = core::mem::replace(&mut self.input, *frame);
+        if self.paused {
+            return;
+        }
+
+        self.advance_clock();
+
+        if frame.pressed(&previous, Button::Hold) {
+            self.hold();
+        }
+
+        if frame.pressed(&previous, Button::RotateLeft) {
+            self.rotate_left();
+        }
+
+        if frame.pressed(&previous, Button::RotateRight) {
+            self.rotate_right();
+        }
+
+        if frame.pressed(&previous, Button::Rotate180) {
+            self.rotate_180();
+        }
+
+        self.set_left(frame.held(Button::Left));
+        self.set_right(frame.held(Button::Right));
+        self.set_drop(frame.held(Button::SoftDrop));
+
+        if frame.pressed(&previous, Button::HardDrop) {
+            self.hard_drop();
+        }
+
+        self.step();
+    }
+
+    // Buttons held during the last tick
+    pub fn input(&self) -> InputFrame {
+        self.input
+    }
+
     pub fn update(&mut self) {
-        if self.line_clears.1 > 0 {
-            self.line_clear_count += self.line_clears.1 as u32;
-            self.line_clear_total += self.line_clears.1 as u32;
-            let limit = self.ruleset.line_clear_constant + (self.level * self.ruleset.line_clear_coeff);
-            if self.line_clear_count >= limit {
-                self.line_clear_count = 0;
-                self.level += 1;
-                self.update_gravity();
-            }
-
-            for i in (0..self.line_clears.1).rev() {
-                let l = self.line_clears.0[i] as usize;
-                for i in l..39 {
-                    self.playfield_mask[i] = self.playfield_mask[i+1];
-                    for c in 0..10 {
-                        self.playfield_colors[i][c] = self.playfield_colors[i+1][c];
-                    }
-                }
+        if self.paused {
+            return;
+        }
 
-                self.playfield_mask[39] = 0b1111110000000000;
-                for c in 0..10 {
-                    self.playfield_colors[39][c] = (0, 0, 0);
-                }
+        self.advance_clock();
+        self.step();
+    }
+
+    // Counts the tick before anything happens in it, so a game finished during the tick is
+    // stamped with it
+    fn advance_clock(&mut self) {
+        if self.game_ended {
+            return;
+        }
+
+        self.ticks += 1;
+        if let Some(limit) = self.ruleset.mode.time_limit() {
+            if self.ticks >= limit {
+                self.finish(Outcome::Completed);
             }
+        }
+    }
 
-            self.line_clears.1 = 0;
-            self.update_ghost();
+    fn step(&mut self) {
+        if self.game_ended {
             return;
         }
 
+        let waiting = if self.lock_tick == self.ticks && !self.piece_active() {
+            // Locked by an input earlier in this tick, which doesn't count towards the delay
+            true
+        } else if self.line_clears.1 > 0 {
+            if self.line_clear_ticks > 1 {
+                self.line_clear_ticks -= 1;
+                true
+            } else {
+                self.collapse_lines();
+                // Line ARE starts once the rows are gone
+                self.spawn_ticks != Some(0)
+            }
+        } else if let Some(ticks) = self.spawn_ticks {
+            if ticks > 1 {
+                self.spawn_ticks = Some(ticks - 1);
+            }
+            ticks > 1
+        } else {
+            false
+        };
+
+        if waiting {
+            // DAS charges between pieces so the next one can shift as soon as it spawns
+            if self.ruleset.preserve_das && self.held_direction != HeldDirection::None {
+                self.das_ticks = (self.das_ticks + 1).min(self.ruleset.das_delay);
+            }
+            return;
+        }
 
-        let mut g = self.gravity;
-        if self.drop_held {
-            g *= self.ruleset.drop_gravity_multipler;
+        if self.spawn_ticks.is_some() {
+            self.spawn_next();
         }
-        
-        self.movement += g;
-        while self.movement > 1.0 {
-            if self.current_piece.y > 0 {
-                self.current_piece.y -= 1;
-                if self.current_piece.collides(&self.playfield_mask) {
-                    self.current_piece.y += 1;
+
+        let g = if self.drop_held {
+            self.ruleset.soft_drop.gravity(self.gravity)
+        } else {
+            self.gravity
+        };
+
+        if g >= TWENTY_G {
+            self.drop_to_ghost();
+            self.movement = 0.0;
+        } else {
+            self.movement += g;
+            while self.movement >= 1.0 {
+                if self.current_piece.y > 0 {
+                    self.current_piece.y -= 1;
+                    if self.current_piece.collides(&self.playfield_mask) {
+                        self.current_piece.y += 1;
+                    } else {
+                        // Stepping down a row gives the piece its full lock delay again
+                        self.lock_ticks = 0;
+                        self.last_rotation = None;
+                        if self.drop_held {
+                            self.score += self.ruleset.scoring.soft_drop;
+                        }
+                    }
                 }
-            }
 
-            self.movement -= 1.0;
+                self.movement -= 1.0;
+            }
         }
 
+        let das_cut = self.das_cut_ticks > 0;
+        self.das_cut_ticks = self.das_cut_ticks.saturating_sub(1);
+
         if self.held_direction != HeldDirection::None {
             if self.das_ticks < self.ruleset.das_delay {
                 self.das_ticks += 1;
+                if self.das_ticks == self.ruleset.das_delay {
+                    // The first auto shift comes on the tick DAS finishes charging
+                    self.arr_ticks = self.ruleset.arr.saturating_sub(1);
+                }
+            }
 
+            if self.das_ticks < self.ruleset.das_delay || das_cut {
+                // Still charging, or holding off after a rotation so it isn't followed by an
+                // unwanted shift
+            } else if self.ruleset.arr == 0 {
+                // Instant, straight to the wall
+                while self.auto_shift() {}
             } else {
-                self.das_movement += self.ruleset.das_gravity;
-                while self.das_movement > 1.0 {
-                    match &self.held_direction {
-                        HeldDirection::None => unreachable!(),
-                        HeldDirection::Left => {self.move_left()},
-                        HeldDirection::Right => {self.move_right()},
-                    }
-        
-                    self.das_movement -= 1.0;
+                self.arr_ticks += 1;
+                if self.arr_ticks >= self.ruleset.arr {
+                    self.arr_ticks = 0;
+                    self.auto_shift();
                 }
             }
         }
@@ -587,18 +1399,93 @@ impl<RNG: Randomizer, ROT: Rotate> Game<RNG, ROT> {
             let piece = self.current_piece.clone();
             self.lock_piece(&piece);
         }
+    }
+}
 
-        // Check line clears
-        for (i, l) in self.playfield_mask.iter().enumerate() {
-            if !(*l) == 0 {
-                // Full line
-                self.line_clears.0[self.line_clears.1] = i as u32;
-                self.line_clears.1 += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomGenerator, SeededRng};
+    use crate::rotate::SuperRotationSystem;
+    use crate::ruleset::LockOutRule;
 
-                if self.line_clears.1 >= 4 {
+    #[test]
+    fn block_out_at_20g() {
+        for lock_out in [LockOutRule::Guideline, LockOutRule::Off] {
+            let ruleset = Ruleset::builder().lock_out(lock_out).build().unwrap();
+            let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(1, SuperRotationSystem::default(), ruleset);
+            game.gravity = 25.0;
+
+            // Pieces stack up in the middle until one spawns into the stack
+            for _ in 0..10_000 {
+                game.tick(&InputFrame::empty());
+                if !game.running() {
                     break;
                 }
             }
+
+            assert!(!game.running());
+            assert_eq!(game.game_over_reason(), Some(GameOverReason::BlockOut));
         }
     }
+
+    // Ticks on which the piece moved while Right was held from the first tick, along with
+    // how far it got. It starts three columns from the wall.
+    fn shift_ticks(das_delay: u32, arr: u32) -> ([u32; 3], u32) {
+        let ruleset = Ruleset::builder().das_delay(das_delay).arr(arr).build().unwrap();
+        let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(1, SuperRotationSystem::default(), ruleset);
+        game.gravity = 0.0;
+
+        let start = game.current_piece.x;
+        let mut moves = [0; 3];
+        let mut count = 0;
+        let mut x = start;
+        for tick in 1..=40 {
+            game.tick(&InputFrame::empty().with(Button::Right, true));
+            if game.current_piece.x != x {
+                moves[count] = tick;
+                count += 1;
+            }
+            x = game.current_piece.x;
+        }
+        (moves, x.abs_diff(start))
+    }
+
+    // Ticks from the lock to the next spawn
+    fn are_after_lock(hard_drop: bool) -> u32 {
+        let ruleset = Ruleset::builder().are(10).lock_delay(5).build().unwrap();
+        let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(1, SuperRotationSystem::default(), ruleset);
+        game.gravity = 25.0;
+
+        let mut locked = None;
+        for tick in 1..=40 {
+            game.tick(&InputFrame::empty().with(Button::HardDrop, hard_drop && tick == 3));
+            while let Some(event) = game.poll_event() {
+                match event {
+                    GameEvent::PieceLocked { .. } => locked = Some(tick),
+                    GameEvent::PieceSpawned { .. } => {
+                        if let Some(locked) = locked {
+                            return tick - locked;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        panic!("no piece spawned after the lock");
+    }
+
+    #[test]
+    fn are_is_the_same_for_every_lock() {
+        assert_eq!(are_after_lock(true), 10);
+        assert_eq!(are_after_lock(false), 10);
+    }
+
+    #[test]
+    fn auto_shift_starts_when_das_charges() {
+        assert_eq!(shift_ticks(16, 6), ([1, 16, 22], 3));
+        assert_eq!(shift_ticks(18, 2), ([1, 18, 20], 3));
+        // Instant ARR goes to the wall on the tick DAS charges
+        assert_eq!(shift_ticks(10, 0), ([1, 10, 0], 3));
+    }
 }
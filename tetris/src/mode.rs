@@ -0,0 +1,191 @@
+use crate::codec::{CodecError, Reader, Writer};
+use crate::event::GameOverReason;
+use crate::TICKS_PER_SECOND;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    // Won once the lines are cleared, endless without a goal
+    Marathon { lines: Option<u32> },
+    // Clear the lines as fast as possible
+    Sprint { lines: u32 },
+    // Score as much as possible before the time runs out
+    Ultra { ticks: u32 },
+    // Starts with rows of garbage and is won once they are all cleared
+    Dig { rows: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Remaining {
+    Lines(u32),
+    Ticks(u32),
+}
+
+impl GameMode {
+    pub fn endless() -> Self {
+        GameMode::Marathon { lines: None }
+    }
+
+    // 150 lines, which is level 15 with the fixed goal of 10 lines per level
+    pub fn marathon() -> Self {
+        GameMode::Marathon { lines: Some(150) }
+    }
+
+    pub fn sprint() -> Self {
+        GameMode::Sprint { lines: 40 }
+    }
+
+    pub fn ultra() -> Self {
+        GameMode::ultra_minutes(3)
+    }
+
+    pub fn dig() -> Self {
+        GameMode::Dig { rows: 10 }
+    }
+
+    pub fn ultra_minutes(minutes: u32) -> Self {
+        GameMode::Ultra {
+            ticks: minutes * 60 * TICKS_PER_SECOND,
+        }
+    }
+
+    // Lines that finish the game, if any
+    pub fn line_goal(&self) -> Option<u32> {
+        match self {
+            GameMode::Marathon { lines } => *lines,
+            GameMode::Sprint { lines } => Some(*lines),
+            GameMode::Ultra { .. } | GameMode::Dig { .. } => None,
+        }
+    }
+
+    // Garbage rows that have to be cleared to finish the game, if any
+    pub fn garbage_goal(&self) -> Option<u32> {
+        match self {
+            GameMode::Dig { rows } => Some(*rows),
+            _ => None,
+        }
+    }
+
+    // Ticks that finish the game, if any
+    pub fn time_limit(&self) -> Option<u32> {
+        match self {
+            GameMode::Ultra { ticks } => Some(*ticks),
+            _ => None,
+        }
+    }
+
+    pub fn remaining(&self, lines: u32, garbage_lines: u32, ticks: u32) -> Option<Remaining> {
+        if let Some(goal) = self.line_goal() {
+            Some(Remaining::Lines(goal.saturating_sub(lines)))
+        } else if let Some(goal) = self.garbage_goal() {
+            Some(Remaining::Lines(goal.saturating_sub(garbage_lines)))
+        } else {
+            self.time_limit()
+                .map(|limit| Remaining::Ticks(limit.saturating_sub(ticks)))
+        }
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        match self {
+            GameMode::Marathon { lines } => {
+                w.u8(0)?;
+                w.u32(lines.unwrap_or(0))
+            }
+            GameMode::Sprint { lines } => {
+                w.u8(1)?;
+                w.u32(*lines)
+            }
+            GameMode::Ultra { ticks } => {
+                w.u8(2)?;
+                w.u32(*ticks)
+            }
+            GameMode::Dig { rows } => {
+                w.u8(3)?;
+                w.u32(*rows)
+            }
+        }
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let tag = r.u8()?;
+        let value = r.u32()?;
+        match tag {
+            0 => Ok(GameMode::Marathon {
+                lines: if value == 0 { None } else { Some(value) },
+            }),
+            1 => Ok(GameMode::Sprint { lines: value }),
+            2 => Ok(GameMode::Ultra { ticks: value }),
+            3 => Ok(GameMode::Dig { rows: value }),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::endless()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // The mode's goal was reached
+    Completed,
+    ToppedOut(GameOverReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameResult {
+    pub outcome: Outcome,
+    pub ticks: u32,
+    pub pieces: u32,
+    pub lines: u32,
+    pub score: u32,
+}
+
+impl GameResult {
+    pub fn seconds(&self) -> f32 {
+        self.ticks as f32 / TICKS_PER_SECOND as f32
+    }
+
+    // Pieces per second
+    pub fn pps(&self) -> f32 {
+        if self.ticks == 0 {
+            0.0
+        } else {
+            self.pieces as f32 / self.seconds()
+        }
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u8(match self.outcome {
+            Outcome::Completed => 0,
+            Outcome::ToppedOut(GameOverReason::BlockOut) => 1,
+            Outcome::ToppedOut(GameOverReason::LockOut) => 2,
+            Outcome::ToppedOut(GameOverReason::GarbageOut) => 3,
+            Outcome::ToppedOut(GameOverReason::PartialLockOut) => 4,
+        })?;
+        w.u32(self.ticks)?;
+        w.u32(self.pieces)?;
+        w.u32(self.lines)?;
+        w.u32(self.score)
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let outcome = match r.u8()? {
+            0 => Outcome::Completed,
+            1 => Outcome::ToppedOut(GameOverReason::BlockOut),
+            2 => Outcome::ToppedOut(GameOverReason::LockOut),
+            3 => Outcome::ToppedOut(GameOverReason::GarbageOut),
+            4 => Outcome::ToppedOut(GameOverReason::PartialLockOut),
+            _ => return Err(CodecError::InvalidValue),
+        };
+
+        Ok(GameResult {
+            outcome,
+            ticks: r.u32()?,
+            pieces: r.u32()?,
+            lines: r.u32()?,
+            score: r.u32()?,
+        })
+    }
+}
@@ -1,11 +1,95 @@
-use rand::{seq::SliceRandom, Rng};
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
 
+use crate::codec::{CodecError, Reader, Writer};
 use crate::Piece;
 
 pub trait Randomizer {
     fn get_next_piece(&mut self) -> Piece;
+
+    // Fills `pieces` with what is coming next without using them up
+    fn peek(&self, pieces: &mut [Piece])
+    where
+        Self: Clone,
+    {
+        let mut ahead = self.clone();
+        for p in pieces.iter_mut() {
+            *p = ahead.get_next_piece();
+        }
+    }
+}
+
+// Randomizers that produce the same sequence every time for a given seed
+pub trait SeedableRandomizer: Randomizer {
+    fn from_seed(seed: u64) -> Self;
+}
+
+// Randomizers that can save and restore exactly where they are in their sequence
+pub trait StatefulRandomizer: Randomizer {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError>;
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError>;
+}
+
+// SplitMix64, small and fast with a state that is just the seed and the number of draws
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
 }
 
+impl SeedableRng for SeededRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        SeededRng::new(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(state: u64) -> Self {
+        SeededRng::new(state)
+    }
+}
+
+// The same seed for everyone on the same day, counted however the frontend likes,
+// e.g. days since the Unix epoch
+pub fn daily_seed(day: u32) -> u64 {
+    SeededRng::new(day as u64).next_u64()
+}
+
+#[derive(Debug, Clone)]
 pub struct RandomGenerator<R> where R: Rng + Sized {
     bag: [Piece; 7],
     index: usize,
@@ -40,6 +124,12 @@ impl<R> RandomGenerator<R> where R: Rng + Sized {
     }
 }
 
+impl<R> SeedableRandomizer for RandomGenerator<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        RandomGenerator::new(R::seed_from_u64(seed))
+    }
+}
+
 impl<R> Randomizer for RandomGenerator<R> where R: Rng + Sized {
     fn get_next_piece(&mut self) -> Piece {
         if self.index == 7 {
@@ -51,3 +141,350 @@ impl<R> Randomizer for RandomGenerator<R> where R: Rng + Sized {
         p
     }
 }
+
+impl StatefulRandomizer for RandomGenerator<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        for p in self.bag {
+            w.u8(p as u8)?;
+        }
+        w.u8(self.index as u8)?;
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        for p in self.bag.iter_mut() {
+            *p = Piece::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+        }
+        self.index = r.u8()? as usize;
+        if self.index > 7 {
+            return Err(CodecError::InvalidValue);
+        }
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
+
+// Ranges are sampled as u32, usize ones roll differently on 32 and 64 bit targets and
+// replays would no longer play back the same everywhere
+fn random_piece<R: Rng>(rng: &mut R) -> Piece {
+    Piece::ALL[rng.gen_range(0..7u32) as usize]
+}
+
+fn write_pieces(w: &mut Writer, pieces: &[Piece]) -> Result<(), CodecError> {
+    for p in pieces {
+        w.u8(*p as u8)?;
+    }
+    Ok(())
+}
+
+fn read_pieces(r: &mut Reader, pieces: &mut [Piece]) -> Result<(), CodecError> {
+    for p in pieces.iter_mut() {
+        *p = Piece::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+    }
+    Ok(())
+}
+
+// Every piece is equally likely every time, droughts and floods included
+#[derive(Debug, Clone)]
+pub struct UniformRandomizer<R> where R: Rng + Sized {
+    rng: R,
+}
+
+impl<R> UniformRandomizer<R> where R: Rng + Sized {
+    pub fn new(rng: R) -> Self {
+        UniformRandomizer { rng }
+    }
+}
+
+impl<R> Randomizer for UniformRandomizer<R> where R: Rng + Sized {
+    fn get_next_piece(&mut self) -> Piece {
+        random_piece(&mut self.rng)
+    }
+}
+
+impl<R> SeedableRandomizer for UniformRandomizer<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        UniformRandomizer::new(R::seed_from_u64(seed))
+    }
+}
+
+impl StatefulRandomizer for UniformRandomizer<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
+
+// NES: roll one of 8, and roll one of 7 again on a repeat or the spare 8th value
+#[derive(Debug, Clone)]
+pub struct NesRandomizer<R> where R: Rng + Sized {
+    last: Option<Piece>,
+    rng: R,
+}
+
+impl<R> NesRandomizer<R> where R: Rng + Sized {
+    pub fn new(rng: R) -> Self {
+        NesRandomizer { last: None, rng }
+    }
+}
+
+impl<R> Randomizer for NesRandomizer<R> where R: Rng + Sized {
+    fn get_next_piece(&mut self) -> Piece {
+        let p = match Piece::from_index(self.rng.gen_range(0..8)) {
+            Some(p) if Some(p) != self.last => p,
+            _ => random_piece(&mut self.rng),
+        };
+
+        self.last = Some(p);
+        p
+    }
+}
+
+impl<R> SeedableRandomizer for NesRandomizer<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        NesRandomizer::new(R::seed_from_u64(seed))
+    }
+}
+
+impl StatefulRandomizer for NesRandomizer<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u8(self.last.map_or(0xff, |p| p as u8))?;
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        self.last = match r.u8()? {
+            0xff => None,
+            i => Some(Piece::from_index(i).ok_or(CodecError::InvalidValue)?),
+        };
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
+
+// TGM1: remembers the last 4 pieces and rolls up to 4 times for one that isn't among them
+#[derive(Debug, Clone)]
+pub struct HistoryRandomizer<R> where R: Rng + Sized {
+    history: [Piece; 4],
+    rolls: u32,
+    first: bool,
+    rng: R,
+}
+
+impl<R> HistoryRandomizer<R> where R: Rng + Sized {
+    pub fn new(rng: R) -> Self {
+        HistoryRandomizer {
+            history: [Piece::Z; 4],
+            rolls: 4,
+            first: true,
+            rng,
+        }
+    }
+
+    fn push_history(&mut self, piece: Piece) {
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+    }
+}
+
+impl<R> Randomizer for HistoryRandomizer<R> where R: Rng + Sized {
+    fn get_next_piece(&mut self) -> Piece {
+        // The first piece is never one that can only be placed with an overhang
+        if self.first {
+            self.first = false;
+            let p = [Piece::I, Piece::J, Piece::L, Piece::T][self.rng.gen_range(0..4u32) as usize];
+            self.push_history(p);
+            return p;
+        }
+
+        let mut p = random_piece(&mut self.rng);
+        for _ in 1..self.rolls {
+            if !self.history.contains(&p) {
+                break;
+            }
+            p = random_piece(&mut self.rng);
+        }
+
+        self.push_history(p);
+        p
+    }
+}
+
+impl<R> SeedableRandomizer for HistoryRandomizer<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        HistoryRandomizer::new(R::seed_from_u64(seed))
+    }
+}
+
+impl StatefulRandomizer for HistoryRandomizer<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        write_pieces(w, &self.history)?;
+        w.bool(self.first)?;
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        read_pieces(r, &mut self.history)?;
+        self.first = r.bool()?;
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
+
+// TGM3: a pool of 35 pieces with a 4 piece history, rejected rolls and every pick put the
+// piece that has gone longest without showing up back into the pool
+#[derive(Debug, Clone)]
+pub struct Tgm3Randomizer<R> where R: Rng + Sized {
+    pool: [Piece; 35],
+    history: [Piece; 4],
+    // Least recently seen first
+    order: [Piece; 7],
+    first: bool,
+    rng: R,
+}
+
+impl<R> Tgm3Randomizer<R> where R: Rng + Sized {
+    pub fn new(rng: R) -> Self {
+        let mut pool = [Piece::I; 35];
+        for (i, p) in pool.iter_mut().enumerate() {
+            *p = Piece::ALL[i / 5];
+        }
+
+        Tgm3Randomizer {
+            pool,
+            history: [Piece::S, Piece::Z, Piece::S, Piece::Z],
+            order: [Piece::J, Piece::I, Piece::Z, Piece::L, Piece::O, Piece::T, Piece::S],
+            first: true,
+            rng,
+        }
+    }
+
+    fn seen(&mut self, piece: Piece) {
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+
+        if let Some(i) = self.order.iter().position(|p| *p == piece) {
+            self.order[i..].rotate_left(1);
+        }
+    }
+}
+
+impl<R> Randomizer for Tgm3Randomizer<R> where R: Rng + Sized {
+    fn get_next_piece(&mut self) -> Piece {
+        if self.first {
+            self.first = false;
+            let p = [Piece::I, Piece::J, Piece::L, Piece::T][self.rng.gen_range(0..4u32) as usize];
+            self.seen(p);
+            return p;
+        }
+
+        let mut index = 0;
+        for roll in 0..6 {
+            index = self.rng.gen_range(0..35u32) as usize;
+            if !self.history.contains(&self.pool[index]) || roll == 5 {
+                break;
+            }
+            self.pool[index] = self.order[0];
+        }
+
+        let p = self.pool[index];
+        self.seen(p);
+        self.pool[index] = self.order[0];
+        p
+    }
+}
+
+impl<R> SeedableRandomizer for Tgm3Randomizer<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        Tgm3Randomizer::new(R::seed_from_u64(seed))
+    }
+}
+
+impl StatefulRandomizer for Tgm3Randomizer<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        write_pieces(w, &self.pool)?;
+        write_pieces(w, &self.history)?;
+        write_pieces(w, &self.order)?;
+        w.bool(self.first)?;
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        read_pieces(r, &mut self.pool)?;
+        read_pieces(r, &mut self.history)?;
+        read_pieces(r, &mut self.order)?;
+        self.first = r.bool()?;
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
+
+// Two of every piece per bag, looser than the 7-bag but still without long droughts
+#[derive(Debug, Clone)]
+pub struct DoubleBagRandomizer<R> where R: Rng + Sized {
+    bag: [Piece; 14],
+    index: usize,
+    rng: R,
+}
+
+impl<R> DoubleBagRandomizer<R> where R: Rng + Sized {
+    fn generate_bag(&mut self) {
+        for (i, p) in self.bag.iter_mut().enumerate() {
+            *p = Piece::ALL[i % 7];
+        }
+
+        self.bag.shuffle(&mut self.rng);
+        self.index = 0;
+    }
+
+    pub fn new(rng: R) -> Self {
+        let mut rng = DoubleBagRandomizer {
+            bag: [Piece::I; 14],
+            index: 0,
+            rng,
+        };
+        rng.generate_bag();
+
+        rng
+    }
+}
+
+impl<R> Randomizer for DoubleBagRandomizer<R> where R: Rng + Sized {
+    fn get_next_piece(&mut self) -> Piece {
+        if self.index == self.bag.len() {
+            self.generate_bag();
+        }
+
+        let p = self.bag[self.index];
+        self.index += 1;
+        p
+    }
+}
+
+impl<R> SeedableRandomizer for DoubleBagRandomizer<R> where R: Rng + SeedableRng + Sized {
+    fn from_seed(seed: u64) -> Self {
+        DoubleBagRandomizer::new(R::seed_from_u64(seed))
+    }
+}
+
+impl StatefulRandomizer for DoubleBagRandomizer<SeededRng> {
+    fn save_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        write_pieces(w, &self.bag)?;
+        w.u8(self.index as u8)?;
+        w.u64(self.rng.state)
+    }
+
+    fn load_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        read_pieces(r, &mut self.bag)?;
+        self.index = r.u8()? as usize;
+        if self.index > self.bag.len() {
+            return Err(CodecError::InvalidValue);
+        }
+        self.rng.state = r.u64()?;
+        Ok(())
+    }
+}
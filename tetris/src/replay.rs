@@ -0,0 +1,223 @@
+use crate::codec::{CodecError, Reader, Writer};
+use crate::event::GameEvent;
+use crate::input::InputFrame;
+use crate::random::SeedableRandomizer;
+use crate::rotate::Rotate;
+use crate::ruleset::Ruleset;
+use crate::Game;
+
+// Header: magic, version, seed, tick count, ruleset
+// Body: runs of (input bits, number of ticks) until the end of the data
+const MAGIC: [u8; 4] = *b"LTRP";
+const VERSION: u8 = 9;
+
+pub struct ReplayRecorder<'a, RNG, ROT>
+where
+    RNG: SeedableRandomizer,
+    ROT: Rotate,
+{
+    game: Game<RNG, ROT>,
+    writer: Writer<'a>,
+    seed: u64,
+    ticks: u32,
+    ticks_position: usize,
+    previous: InputFrame,
+    run: u16,
+}
+
+impl<'a, RNG: SeedableRandomizer, ROT: Rotate> ReplayRecorder<'a, RNG, ROT> {
+    pub fn new(buf: &'a mut [u8], seed: u64, ruleset: Ruleset, rot: ROT) -> Result<Self, CodecError> {
+        let mut writer = Writer::new(buf);
+        writer.bytes(&MAGIC)?;
+        writer.u8(VERSION)?;
+        writer.u64(seed)?;
+        let ticks_position = writer.position();
+        writer.u32(0)?;
+        ruleset.encode(&mut writer)?;
+
+        let game = Game::from_seed(seed, rot, ruleset);
+
+        Ok(ReplayRecorder {
+            game,
+            writer,
+            seed,
+            ticks: 0,
+            ticks_position,
+            previous: InputFrame::empty(),
+            run: 0,
+        })
+    }
+
+    pub fn game(&self) -> &Game<RNG, ROT> {
+        &self.game
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    pub fn poll_event(&mut self) -> Option<GameEvent> {
+        self.game.poll_event()
+    }
+
+    pub fn tick(&mut self, frame: InputFrame) -> Result<(), CodecError> {
+        if self.run > 0 && (frame != self.previous || self.run == u16::MAX) {
+            self.flush()?;
+        }
+
+        self.game.tick(&frame);
+        self.previous = frame;
+        self.run += 1;
+        self.ticks += 1;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), CodecError> {
+        self.writer.u8(self.previous.bits())?;
+        self.writer.u16(self.run)?;
+        self.run = 0;
+        Ok(())
+    }
+
+    // Returns the length of the finished replay in the buffer
+    pub fn finish(mut self) -> Result<usize, CodecError> {
+        if self.run > 0 {
+            self.flush()?;
+        }
+
+        self.writer.patch(self.ticks_position, &self.ticks.to_le_bytes())?;
+        Ok(self.writer.position())
+    }
+}
+
+pub struct ReplayPlayer<'a, RNG, ROT>
+where
+    RNG: SeedableRandomizer,
+    ROT: Rotate,
+{
+    game: Game<RNG, ROT>,
+    reader: Reader<'a>,
+    seed: u64,
+    ticks: u32,
+    total_ticks: u32,
+    frame: InputFrame,
+    run: u16,
+}
+
+impl<'a, RNG: SeedableRandomizer, ROT: Rotate> ReplayPlayer<'a, RNG, ROT> {
+    pub fn new(data: &'a [u8], rot: ROT) -> Result<Self, CodecError> {
+        let mut reader = Reader::new(data);
+        if reader.bytes::<4>()? != MAGIC {
+            return Err(CodecError::BadMagic);
+        }
+
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(CodecError::UnsupportedVersion(version));
+        }
+
+        let seed = reader.u64()?;
+        let total_ticks = reader.u32()?;
+        let ruleset = Ruleset::decode(&mut reader)?;
+
+        let game = Game::from_seed(seed, rot, ruleset);
+
+        Ok(ReplayPlayer {
+            game,
+            reader,
+            seed,
+            ticks: 0,
+            total_ticks,
+            frame: InputFrame::empty(),
+            run: 0,
+        })
+    }
+
+    pub fn game(&self) -> &Game<RNG, ROT> {
+        &self.game
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    pub fn total_ticks(&self) -> u32 {
+        self.total_ticks
+    }
+
+    pub fn finished(&self) -> bool {
+        self.ticks >= self.total_ticks
+    }
+
+    pub fn poll_event(&mut self) -> Option<GameEvent> {
+        self.game.poll_event()
+    }
+
+    // Plays back one tick, returns false once the recording is over
+    pub fn tick(&mut self) -> Result<bool, CodecError> {
+        if self.finished() {
+            return Ok(false);
+        }
+
+        if self.run == 0 {
+            self.frame = InputFrame::from_bits(self.reader.u8()?);
+            self.run = self.reader.u16()?;
+            if self.run == 0 {
+                return Err(CodecError::InvalidValue);
+            }
+        }
+
+        self.game.tick(&self.frame);
+        self.run -= 1;
+        self.ticks += 1;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomGenerator, SeededRng};
+    use crate::rotate::SuperRotationSystem;
+
+    type Generator = RandomGenerator<SeededRng>;
+
+    #[test]
+    fn playback_matches_recording() {
+        let mut buf = [0; 8192];
+        let mut recorder: ReplayRecorder<Generator, _> =
+            ReplayRecorder::new(&mut buf, 42, Ruleset::guideline(), SuperRotationSystem::default())
+                .unwrap();
+
+        // Runs of pseudo random inputs with idle stretches in between
+        let mut state: u32 = 7;
+        for _ in 0..5000 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            let bits = if (state >> 10) & 3 == 0 { (state >> 16) as u8 & 0x7f } else { 0 };
+            recorder.tick(InputFrame::from_bits(bits)).unwrap();
+            if !recorder.game().running() {
+                break;
+            }
+        }
+
+        let recorded = recorder.game().snapshot().unwrap();
+        let ticks = recorder.ticks();
+        let len = recorder.finish().unwrap();
+
+        let mut player: ReplayPlayer<Generator, _> =
+            ReplayPlayer::new(&buf[..len], SuperRotationSystem::default()).unwrap();
+        while player.tick().unwrap() {}
+
+        assert_eq!(player.ticks(), ticks);
+        assert!(player.game().pieces_placed() > 0);
+        assert_eq!(player.game().snapshot().unwrap().as_bytes(), recorded.as_bytes());
+    }
+}
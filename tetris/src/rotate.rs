@@ -1,153 +1,505 @@
-use crate::{CurrentPiece, Piece, PlayfieldMask, Rotation};
+use crate::{CurrentPiece, Piece, PlayfieldMask, Rotation, Shape, ShapeTable};
+
+// The rotated piece and the index of the kick test that succeeded, 0 being no kick
+pub type Rotated = (CurrentPiece, usize);
 
 pub trait Rotate {
-    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<CurrentPiece>;
-    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<CurrentPiece>;
+    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated>;
+    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated>;
+
+    // Not every rotation system can turn a piece around in one go
+    fn rotate_180(&self, _piece: &CurrentPiece, _playfield: &PlayfieldMask) -> Option<Rotated> {
+        None
+    }
+
+    // Shapes new pieces spawn with
+    fn shapes(&self) -> &'static ShapeTable {
+        &SRS_SHAPES
+    }
 }
 
-pub struct SuperRotationSystem {}
+// JLSTZ rotate in a 3x3 box and I in a 4x4 box, spawning flat side down
+pub const SRS_SHAPES: ShapeTable = [
+    // I
+    [
+        Shape::new([0b1111, 0, 0, 0], 0, 2),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 2, 0),
+        Shape::new([0b1111, 0, 0, 0], 0, 1),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 1, 0),
+    ],
+    // J
+    [
+        Shape::new([0b111, 0b001, 0, 0], 0, 1),
+        Shape::new([0b01, 0b01, 0b11, 0], 1, 0),
+        Shape::new([0b100, 0b111, 0, 0], 0, 0),
+        Shape::new([0b11, 0b10, 0b10, 0], 0, 0),
+    ],
+    // L
+    [
+        Shape::new([0b111, 0b100, 0, 0], 0, 1),
+        Shape::new([0b11, 0b01, 0b01, 0], 1, 0),
+        Shape::new([0b001, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b10, 0b11, 0], 0, 0),
+    ],
+    // O
+    [
+        Shape::new([0b11, 0b11, 0, 0], 1, 1),
+        Shape::new([0b11, 0b11, 0, 0], 1, 1),
+        Shape::new([0b11, 0b11, 0, 0], 1, 1),
+        Shape::new([0b11, 0b11, 0, 0], 1, 1),
+    ],
+    // S
+    [
+        Shape::new([0b011, 0b110, 0, 0], 0, 1),
+        Shape::new([0b10, 0b11, 0b01, 0], 1, 0),
+        Shape::new([0b011, 0b110, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b01, 0], 0, 0),
+    ],
+    // T
+    [
+        Shape::new([0b111, 0b010, 0, 0], 0, 1),
+        Shape::new([0b01, 0b11, 0b01, 0], 1, 0),
+        Shape::new([0b010, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b10, 0], 0, 0),
+    ],
+    // Z
+    [
+        Shape::new([0b110, 0b011, 0, 0], 0, 1),
+        Shape::new([0b01, 0b11, 0b10, 0], 1, 0),
+        Shape::new([0b110, 0b011, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b10, 0], 0, 0),
+    ],
+];
+
+// Every piece rests on the bottom of its box and JLT spawn flat side up
+pub const ARS_SHAPES: ShapeTable = [
+    // I
+    [
+        Shape::new([0b1111, 0, 0, 0], 0, 2),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 2, 0),
+        Shape::new([0b1111, 0, 0, 0], 0, 2),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 2, 0),
+    ],
+    // J
+    [
+        Shape::new([0b100, 0b111, 0, 0], 0, 0),
+        Shape::new([0b11, 0b10, 0b10, 0], 0, 0),
+        Shape::new([0b111, 0b001, 0, 0], 0, 0),
+        Shape::new([0b01, 0b01, 0b11, 0], 1, 0),
+    ],
+    // L
+    [
+        Shape::new([0b001, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b10, 0b11, 0], 0, 0),
+        Shape::new([0b111, 0b100, 0, 0], 0, 0),
+        Shape::new([0b11, 0b01, 0b01, 0], 1, 0),
+    ],
+    // O
+    [
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+    ],
+    // S
+    [
+        Shape::new([0b011, 0b110, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b01, 0], 0, 0),
+        Shape::new([0b011, 0b110, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b01, 0], 0, 0),
+    ],
+    // T
+    [
+        Shape::new([0b010, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b10, 0], 0, 0),
+        Shape::new([0b111, 0b010, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b01, 0], 1, 0),
+    ],
+    // Z
+    [
+        Shape::new([0b110, 0b011, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b10, 0], 1, 0),
+        Shape::new([0b110, 0b011, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b10, 0], 1, 0),
+    ],
+];
+
+// Kick tests for each piece and the rotation it turns from, in the order they are tried
+pub type KickTable = [[&'static [(i32, i32)]; 4]; 7];
+
+// Everything a rotation system needs, the piece turns inside its rotation box then tries
+// each kick in order. (0, 0) has to be listed to allow turning in place and an empty list
+// disables that rotation
+#[derive(Debug, Clone)]
+pub struct RotationTable {
+    pub shapes: ShapeTable,
+    pub right: KickTable,
+    pub left: KickTable,
+    pub flip: KickTable,
+}
+
+impl RotationTable {
+    fn turn(&self, piece: &CurrentPiece, playfield: &PlayfieldMask, rotation: Rotation, kicks: &[(i32, i32)]) -> Option<Rotated> {
+        let mut rotated = piece.clone();
+        rotated.rotation = rotation;
+
+        // Keep the box in place, so the piece moves by the difference in offsets
+        let from = &self.shapes[piece.piece as usize][piece.rotation as usize];
+        let to = &self.shapes[piece.piece as usize][rotation as usize];
+        let x = piece.x as i32 - from.offset.0 as i32 + to.offset.0 as i32;
+        let y = piece.y as i32 - from.offset.1 as i32 + to.offset.1 as i32;
+
+        for (kick, test) in kicks.iter().enumerate() {
+            let x_tmp = x + test.0;
+            let y_tmp = y + test.1;
+
+            if x_tmp >= 0 && y_tmp >= 0 {
+                rotated.x = x_tmp as u32;
+                rotated.y = y_tmp as u32;
+                if !rotated.collides(playfield) {
+                    return Some((rotated, kick));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        let kicks = self.left[piece.piece as usize][piece.rotation as usize];
+        self.turn(piece, playfield, piece.rotation.left(), kicks)
+    }
+
+    pub fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        let kicks = self.right[piece.piece as usize][piece.rotation as usize];
+        self.turn(piece, playfield, piece.rotation.right(), kicks)
+    }
+
+    pub fn rotate_180(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        let kicks = self.flip[piece.piece as usize][piece.rotation as usize];
+        self.turn(piece, playfield, piece.rotation.flip(), kicks)
+    }
+}
+
+const NO_KICKS: [&[(i32, i32)]; 4] = [&[(0, 0)]; 4];
+const NO_ROTATION: [&[(i32, i32)]; 4] = [&[]; 4];
+
+// Indexed by the rotation turned from: 0, R, 2, L
+const SRS_JLSTZ_RIGHT: [&[(i32, i32)]; 4] = [
+    &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const SRS_JLSTZ_LEFT: [&[(i32, i32)]; 4] = [
+    &[(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    &[(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    &[(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const SRS_I_RIGHT: [&[(i32, i32)]; 4] = [
+    &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+];
+
+const SRS_I_LEFT: [&[(i32, i32)]; 4] = [
+    &[(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    &[(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    &[(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+];
+
+// SRS+ as used by TETR.IO, the same for every piece
+const SRS_PLUS_FLIP: [&[(i32, i32)]; 4] = [
+    &[(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    &[(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    &[(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    &[(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+
+// Guideline SRS without 180 rotations
+pub const SRS: RotationTable = RotationTable {
+    shapes: SRS_SHAPES,
+    right: [
+        SRS_I_RIGHT,
+        SRS_JLSTZ_RIGHT,
+        SRS_JLSTZ_RIGHT,
+        NO_KICKS,
+        SRS_JLSTZ_RIGHT,
+        SRS_JLSTZ_RIGHT,
+        SRS_JLSTZ_RIGHT,
+    ],
+    left: [
+        SRS_I_LEFT,
+        SRS_JLSTZ_LEFT,
+        SRS_JLSTZ_LEFT,
+        NO_KICKS,
+        SRS_JLSTZ_LEFT,
+        SRS_JLSTZ_LEFT,
+        SRS_JLSTZ_LEFT,
+    ],
+    flip: [NO_ROTATION; 7],
+};
+
+pub const SRS_PLUS: RotationTable = RotationTable {
+    flip: [
+        SRS_PLUS_FLIP,
+        SRS_PLUS_FLIP,
+        SRS_PLUS_FLIP,
+        NO_KICKS,
+        SRS_PLUS_FLIP,
+        SRS_PLUS_FLIP,
+        SRS_PLUS_FLIP,
+    ],
+    ..SRS
+};
+
+// Rotates with any table, for kick sets the engine doesn't ship
+#[derive(Debug, Clone)]
+pub struct TableRotationSystem {
+    table: &'static RotationTable,
+}
+
+impl TableRotationSystem {
+    pub const fn new(table: &'static RotationTable) -> Self {
+        TableRotationSystem { table }
+    }
+
+    pub fn table(&self) -> &'static RotationTable {
+        self.table
+    }
+}
+
+impl Rotate for TableRotationSystem {
+    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_left(piece, playfield)
+    }
+
+    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_right(piece, playfield)
+    }
+
+    fn rotate_180(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_180(piece, playfield)
+    }
+
+    fn shapes(&self) -> &'static ShapeTable {
+        &self.table.shapes
+    }
+}
+
+// SRS with SRS+ 180 rotations unless they are turned off
+#[derive(Debug, Clone)]
+pub struct SuperRotationSystem {
+    table: &'static RotationTable,
+}
 
 impl SuperRotationSystem {
-    fn rotate(&self, piece: &CurrentPiece, playfield: &PlayfieldMask, clockwise: bool) -> Option<CurrentPiece> {
-        // JLSTZ
-        // 0->R     (0, 0)     (-1, 0)     (-1, +1)     (0, -2)     (-1, -2)
-        // R->2     (0, 0)     (+1, 0)     (+1, -1)     (0, +2)     (+1, +2)
-        // 2->L     (0, 0)     (+1, 0)     (+1, +1)     (0, -2)     (+1, -2)
-        // L->0     (0, 0)     (-1, 0)     (-1, -1)     (0, +2)     (-1, +2)
-
-        // I
-        // 0->R     (0, 0)     (-2, 0)     (+1, 0)     (-2, -1)     (+1, +2)
-        // R->2     (0, 0)     (-1, 0)     (+2, 0)     (-1, +2)     (+2, -1)
-        // 2->L     (0, 0)     (+2, 0)     (-1, 0)     (+2, +1)     (-1, -2)
-        // L->0     (0, 0)     (+1, 0)     (-2, 0)     (+1, -2)     (-2, +1)
-
-        let mut new_position = piece.clone();
-        let pos;
-
-        if clockwise {
-            pos = new_position.rotation;
-            new_position.rotation = new_position.rotation.right();
-        } else {
-            new_position.rotation = new_position.rotation.left();
-            pos = new_position.rotation;
+    pub fn new() -> Self {
+        SuperRotationSystem::default()
+    }
+
+    pub fn without_180() -> Self {
+        SuperRotationSystem { table: &SRS }
+    }
+}
+
+impl Default for SuperRotationSystem {
+    fn default() -> Self {
+        SuperRotationSystem { table: &SRS_PLUS }
+    }
+}
+
+impl Rotate for SuperRotationSystem {
+    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_left(piece, playfield)
+    }
+
+    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_right(piece, playfield)
+    }
+
+    fn rotate_180(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        self.table.rotate_180(piece, playfield)
+    }
+
+    fn shapes(&self) -> &'static ShapeTable {
+        &self.table.shapes
+    }
+}
+
+// Right handed NES orientations, every piece turns around a fixed block and never kicks
+pub const NRS_SHAPES: ShapeTable = [
+    // I
+    [
+        Shape::new([0b1111, 0, 0, 0], 0, 1),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 2, 0),
+        Shape::new([0b1111, 0, 0, 0], 0, 1),
+        Shape::new([0b1, 0b1, 0b1, 0b1], 2, 0),
+    ],
+    // J
+    [
+        Shape::new([0b100, 0b111, 0, 0], 0, 0),
+        Shape::new([0b11, 0b10, 0b10, 0], 0, 0),
+        Shape::new([0b111, 0b001, 0, 0], 0, 1),
+        Shape::new([0b01, 0b01, 0b11, 0], 1, 0),
+    ],
+    // L
+    [
+        Shape::new([0b001, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b10, 0b11, 0], 0, 0),
+        Shape::new([0b111, 0b100, 0, 0], 0, 1),
+        Shape::new([0b11, 0b01, 0b01, 0], 1, 0),
+    ],
+    // O
+    [
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+        Shape::new([0b11, 0b11, 0, 0], 1, 0),
+    ],
+    // S
+    [
+        Shape::new([0b011, 0b110, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b01, 0], 1, 0),
+        Shape::new([0b011, 0b110, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b01, 0], 1, 0),
+    ],
+    // T
+    [
+        Shape::new([0b010, 0b111, 0, 0], 0, 0),
+        Shape::new([0b10, 0b11, 0b10, 0], 0, 0),
+        Shape::new([0b111, 0b010, 0, 0], 0, 1),
+        Shape::new([0b01, 0b11, 0b01, 0], 1, 0),
+    ],
+    // Z
+    [
+        Shape::new([0b110, 0b011, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b10, 0], 1, 0),
+        Shape::new([0b110, 0b011, 0, 0], 0, 0),
+        Shape::new([0b01, 0b11, 0b10, 0], 1, 0),
+    ],
+];
+
+pub const NRS: RotationTable = RotationTable {
+    shapes: NRS_SHAPES,
+    right: [NO_KICKS; 7],
+    left: [NO_KICKS; 7],
+    flip: [NO_ROTATION; 7],
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct NintendoRotationSystem {}
+
+impl Rotate for NintendoRotationSystem {
+    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        NRS.rotate_left(piece, playfield)
+    }
+
+    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
+        NRS.rotate_right(piece, playfield)
+    }
+
+    fn shapes(&self) -> &'static ShapeTable {
+        &NRS.shapes
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ArikaRotationSystem {
+    // TGM3: the I can kick off walls when it ends up flat
+    pub i_wall_kicks: bool,
+    // TGM3: grounded I and T pieces can kick upwards
+    pub floor_kicks: bool,
+}
+
+impl ArikaRotationSystem {
+    // TGM and TGM2, only JLSTZ kick and never upwards
+    pub fn new() -> Self {
+        ArikaRotationSystem::default()
+    }
+
+    pub fn tgm3() -> Self {
+        ArikaRotationSystem {
+            i_wall_kicks: true,
+            floor_kicks: true,
+        }
+    }
+
+    // J, L and T only kick if the first blocked cell, reading the box from the top left, is not in the center column
+    fn center_column_blocked(rotated: &CurrentPiece, box_x: i32, box_y: i32, playfield: &PlayfieldMask) -> bool {
+        let shape = rotated.shape();
+        let (ox, oy) = (shape.offset.0 as i32, shape.offset.1 as i32);
+        for y in (0..3).rev() {
+            for x in 0..3 {
+                let row = y - oy;
+                let column = x - ox;
+                let filled = (0..4).contains(&row)
+                    && column >= 0
+                    && shape.rows[row as usize] & (1 << column) != 0;
+
+                let (px, py) = (box_x + x, box_y + y);
+                let blocked = px < 0
+                    || !(0..40).contains(&py)
+                    || playfield[py as usize] & (1 << px) != 0;
+
+                if filled && blocked {
+                    return x == 1;
+                }
+            }
         }
+        false
+    }
 
-        let tests = match &new_position.piece {
-            Piece::I => match &pos {
-                Rotation::Rotate0 => [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
-                Rotation::Rotate90 => [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
-                Rotation::Rotate180 => [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
-                Rotation::Rotate270 => [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
-            },
-            Piece::J | Piece::L | Piece::O | Piece::S | Piece::T | Piece::Z => match &pos {
-                Rotation::Rotate0 => [(0,  0),  (-1,  0),  (-1, 1),  (0, -2),  (-1, -2)],
-                Rotation::Rotate90 => [(0,  0),  (1,  0),  (1, -1),  (0, 2),  (1, 2)],
-                Rotation::Rotate180 => [(0,  0),  (1,  0),  (1, 1),  (0, -2),  (1, -2)],
-                Rotation::Rotate270 => [(0,  0),  (-1,  0),  (-1, -1),  (0, 2),  (-1, 2)],
-            },
+    fn rotate(&self, piece: &CurrentPiece, playfield: &PlayfieldMask, clockwise: bool) -> Option<Rotated> {
+        let mut rotated = piece.clone();
+        rotated.rotation = if clockwise {
+            piece.rotation.right()
+        } else {
+            piece.rotation.left()
         };
 
-        let mult = if clockwise { 1 } else { -1 };
-
-        let x = (piece.x as i32) + (match &new_position.piece {
-            Piece::I => match &pos {
-                Rotation::Rotate0 => 2,
-                Rotation::Rotate90 => -2,
-                Rotation::Rotate180 => 1,
-                Rotation::Rotate270 => -1,
-            },
-            Piece::J => match &pos {
-                Rotation::Rotate0 => 1,
-                Rotation::Rotate90 => -1,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::L => match &pos {
-                Rotation::Rotate0 => 1,
-                Rotation::Rotate90 => -1,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::O => match &pos {
-                Rotation::Rotate0 => 0,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::S => match &pos {
-                Rotation::Rotate0 => 1,
-                Rotation::Rotate90 => -1,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::T => match &pos {
-                Rotation::Rotate0 => 1,
-                Rotation::Rotate90 => -1,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::Z => match &pos {
-                Rotation::Rotate0 => 1,
-                Rotation::Rotate90 => -1,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-        } * mult);
-
-        let y = (piece.y as i32) + (match &new_position.piece {
-            Piece::I => match &pos {
-                Rotation::Rotate0 => -2,
-                Rotation::Rotate90 => 1,
-                Rotation::Rotate180 => -1,
-                Rotation::Rotate270 => 2,
-            },
-            Piece::J => match &pos {
-                Rotation::Rotate0 => -1,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 1,
-            },
-            Piece::L => match &pos {
-                Rotation::Rotate0 => -1,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 1,
-            },
-            Piece::O => match &pos {
-                Rotation::Rotate0 => 0,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 0,
-            },
-            Piece::S => match &pos {
-                Rotation::Rotate0 => -1,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 1,
-            },
-            Piece::T => match &pos {
-                Rotation::Rotate0 => -1,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 1,
-            },
-            Piece::Z => match &pos {
-                Rotation::Rotate0 => -1,
-                Rotation::Rotate90 => 0,
-                Rotation::Rotate180 => 0,
-                Rotation::Rotate270 => 1,
-            },
-        } * mult);
-        
-        for test in tests {
-            let x_tmp = x + (test.0 * mult);
-            let y_tmp = y + (test.1 * mult);
+        // Rotations keep the box in place, so the piece moves by the difference in offsets
+        let from = piece.shape();
+        let to = rotated.shape();
+        let box_x = piece.x as i32 - from.offset.0 as i32;
+        let box_y = piece.y as i32 - from.offset.1 as i32;
+        let x = box_x + to.offset.0 as i32;
+        let y = box_y + to.offset.1 as i32;
+
+        let mut grounded = piece.clone();
+        grounded.y = grounded.y.wrapping_sub(1);
+        let grounded = piece.y == 0 || grounded.collides(playfield);
+
+        let flat = matches!(rotated.rotation, Rotation::Rotate0 | Rotation::Rotate180);
+        let tests: &[(i32, i32)] = match piece.piece {
+            Piece::I if flat && self.i_wall_kicks => &[(0, 0), (1, 0), (-1, 0), (2, 0)],
+            Piece::I if !flat && self.floor_kicks && grounded => &[(0, 0), (0, 1), (0, 2)],
+            Piece::I => &[(0, 0)],
+            Piece::T if self.floor_kicks && grounded => &[(0, 0), (1, 0), (-1, 0), (0, 1)],
+            _ => &[(0, 0), (1, 0), (-1, 0)],
+        };
+
+        for (kick, test) in tests.iter().enumerate() {
+            if kick == 1
+                && matches!(piece.piece, Piece::J | Piece::L | Piece::T)
+                && Self::center_column_blocked(&rotated, box_x, box_y, playfield)
+            {
+                return None;
+            }
+
+            let x_tmp = x + test.0;
+            let y_tmp = y + test.1;
 
             if x_tmp >= 0 && y_tmp >= 0 {
-                new_position.x = x_tmp as u32;
-                new_position.y = y_tmp as u32;
-                if !new_position.collides(playfield) {
-                    return Some(new_position);
+                rotated.x = x_tmp as u32;
+                rotated.y = y_tmp as u32;
+                if !rotated.collides(playfield) {
+                    return Some((rotated, kick));
                 }
             }
         }
@@ -156,12 +508,124 @@ impl SuperRotationSystem {
     }
 }
 
-impl Rotate for SuperRotationSystem {
-    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<CurrentPiece> {
+impl Rotate for ArikaRotationSystem {
+    fn rotate_left(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
         self.rotate(piece, playfield, false)
     }
 
-    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<CurrentPiece> {
+    fn rotate_right(&self, piece: &CurrentPiece, playfield: &PlayfieldMask) -> Option<Rotated> {
         self.rotate(piece, playfield, true)
     }
-}
\ No newline at end of file
+
+    fn shapes(&self) -> &'static ShapeTable {
+        &ARS_SHAPES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Rotation::{Rotate0 as R0, Rotate180 as R2, Rotate270 as RL, Rotate90 as RR};
+
+    // Rotation turned from and to, then the kicks in order
+    type Turn = (Rotation, Rotation, [(i32, i32); 5]);
+
+    // Blocks of a shape inside its rotation box, sorted so shapes can be compared
+    fn cells(shape: &Shape) -> [(u32, u32); 4] {
+        let mut cells = [(0, 0); 4];
+        let mut n = 0;
+        for (y, row) in shape.rows.iter().enumerate() {
+            for x in 0..4 {
+                if row & (1 << x) != 0 {
+                    cells[n] = (x + shape.offset.0, y as u32 + shape.offset.1);
+                    n += 1;
+                }
+            }
+        }
+        assert_eq!(n, 4);
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn srs_spawn_states() {
+        // Box coordinates from the bottom left, I and O in a 4 wide box and the rest in a 3x3
+        let spawn = [
+            (Piece::I, [(0, 2), (1, 2), (2, 2), (3, 2)]),
+            (Piece::J, [(0, 1), (0, 2), (1, 1), (2, 1)]),
+            (Piece::L, [(0, 1), (1, 1), (2, 1), (2, 2)]),
+            (Piece::O, [(1, 1), (1, 2), (2, 1), (2, 2)]),
+            (Piece::S, [(0, 1), (1, 1), (1, 2), (2, 2)]),
+            (Piece::T, [(0, 1), (1, 1), (1, 2), (2, 1)]),
+            (Piece::Z, [(0, 2), (1, 1), (1, 2), (2, 1)]),
+        ];
+
+        for (piece, expected) in spawn {
+            assert_eq!(cells(&SRS_SHAPES[piece as usize][0]), expected, "{:?}", piece);
+        }
+    }
+
+    #[test]
+    fn srs_states_turn_around_the_box_center() {
+        for piece in Piece::ALL {
+            let size = match piece {
+                Piece::I => 4,
+                Piece::O => continue,
+                _ => 3,
+            };
+
+            for rotation in Rotation::ALL {
+                let mut turned = cells(&SRS_SHAPES[piece as usize][rotation as usize]).map(|(x, y)| (y, size - 1 - x));
+                turned.sort();
+                let next = cells(&SRS_SHAPES[piece as usize][rotation.right() as usize]);
+                assert_eq!(turned, next, "{:?} {:?}", piece, rotation);
+            }
+        }
+    }
+
+    #[test]
+    fn srs_kicks() {
+        // Offsets to try for each turn as listed by the guideline, positive y is up
+        let jlstz: [Turn; 8] = [
+            (R0, RR, [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+            (RR, R0, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+            (RR, R2, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+            (R2, RR, [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+            (R2, RL, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+            (RL, R2, [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+            (RL, R0, [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+            (R0, RL, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+        ];
+        let i: [Turn; 8] = [
+            (R0, RR, [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]),
+            (RR, R0, [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]),
+            (RR, R2, [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]),
+            (R2, RR, [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]),
+            (R2, RL, [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]),
+            (RL, R2, [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]),
+            (RL, R0, [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]),
+            (R0, RL, [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]),
+        ];
+
+        for table in [&SRS, &SRS_PLUS] {
+            for piece in Piece::ALL {
+                let published = match piece {
+                    Piece::I => &i,
+                    Piece::O => {
+                        for rotation in Rotation::ALL {
+                            assert_eq!(table.right[piece as usize][rotation as usize], &[(0, 0)]);
+                            assert_eq!(table.left[piece as usize][rotation as usize], &[(0, 0)]);
+                        }
+                        continue;
+                    }
+                    _ => &jlstz,
+                };
+
+                for (from, to, kicks) in published {
+                    let kick_table = if from.right() == *to { &table.right } else { &table.left };
+                    assert_eq!(kick_table[piece as usize][*from as usize], kicks, "{:?} {:?} {:?}", piece, from, to);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,695 @@
+use core::fmt;
+
+use micromath::F32Ext;
+
+use crate::codec::{CodecError, Reader, Writer};
+use crate::garbage::GarbageHoles;
+use crate::mode::GameMode;
+use crate::{MAX_PREVIEWS, TWENTY_G};
+use crate::score::ScoreTable;
+
+// NTSC NES frames per row, indexed by level and staying at 1 from level 29
+const NES_FRAMES_PER_ROW: [u8; 29] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+
+// TGM1 gravity in 1/256 rows per frame, from the internal level each entry starts at
+const TGM_GRAVITY: [(u16, u16); 30] = [
+    (0, 4), (30, 6), (35, 8), (40, 10), (50, 12), (60, 16), (70, 32), (80, 48), (90, 64),
+    (100, 80), (120, 96), (140, 112), (160, 128), (170, 144), (200, 4), (220, 32), (230, 64),
+    (233, 96), (236, 128), (239, 160), (243, 192), (247, 224), (251, 256), (300, 512),
+    (330, 768), (360, 1024), (400, 1280), (420, 1024), (450, 768), (500, 5120),
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GravityCurve {
+    // (0.8 - level * 0.007) ^ level seconds per row
+    #[default]
+    Guideline,
+    // Frames per row from the NES, level 29 and up is one row every frame
+    Nes,
+    // TGM1's table, each level here counts as ten of its internal levels, 20G from level 50
+    Tgm,
+}
+
+impl GravityCurve {
+    // Rows per tick at a level
+    pub fn gravity(&self, level: u32) -> f32 {
+        match self {
+            // Called through the trait so test builds, which link std, use the same maths
+            GravityCurve::Guideline => {
+                1.0 / (F32Ext::powi(0.8 - ((level as f32) * 0.007), level as i32) * 60.0)
+            }
+            GravityCurve::Nes => {
+                let frames = NES_FRAMES_PER_ROW.get(level as usize).copied().unwrap_or(1);
+                1.0 / frames as f32
+            }
+            GravityCurve::Tgm => {
+                let internal = level.saturating_mul(10);
+                let g = TGM_GRAVITY
+                    .iter()
+                    .rev()
+                    .find(|(start, _)| internal >= *start as u32)
+                    .map_or(4, |(_, g)| *g);
+                g as f32 / 256.0
+            }
+        }
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u8(match self {
+            GravityCurve::Guideline => 0,
+            GravityCurve::Nes => 1,
+            GravityCurve::Tgm => 2,
+        })
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        match r.u8()? {
+            0 => Ok(GravityCurve::Guideline),
+            1 => Ok(GravityCurve::Nes),
+            2 => Ok(GravityCurve::Tgm),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+// How fast a piece falls while soft drop is held
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoftDrop {
+    // The level's gravity times this
+    Multiplier(f32),
+    // Rows per tick whatever the level, unless gravity is already faster
+    Fixed(f32),
+    // Straight down to the ghost without locking, also called sonic drop
+    Instant,
+}
+
+impl SoftDrop {
+    // Rows per tick with soft drop held
+    pub fn gravity(&self, gravity: f32) -> f32 {
+        match self {
+            SoftDrop::Multiplier(m) => gravity * m,
+            SoftDrop::Fixed(rows) => rows.max(gravity),
+            SoftDrop::Instant => TWENTY_G,
+        }
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        match self {
+            SoftDrop::Multiplier(m) => {
+                w.u8(0)?;
+                w.f32(*m)
+            }
+            SoftDrop::Fixed(rows) => {
+                w.u8(1)?;
+                w.f32(*rows)
+            }
+            SoftDrop::Instant => w.u8(2),
+        }
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        match r.u8()? {
+            0 => Ok(SoftDrop::Multiplier(r.f32()?)),
+            1 => Ok(SoftDrop::Fixed(r.f32()?)),
+            2 => Ok(SoftDrop::Instant),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+impl Default for SoftDrop {
+    fn default() -> Self {
+        SoftDrop::Multiplier(20.0)
+    }
+}
+
+// Which pieces locking above the visible playfield end the game
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LockOutRule {
+    // Only a piece entirely above row 20, parts of a piece can stay above it
+    #[default]
+    Guideline,
+    // Any block above row 20
+    Partial,
+    // Pieces can lock anywhere, only block out and garbage out end the game
+    Off,
+}
+
+impl LockOutRule {
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u8(match self {
+            LockOutRule::Guideline => 0,
+            LockOutRule::Partial => 1,
+            LockOutRule::Off => 2,
+        })
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        match r.u8()? {
+            0 => Ok(LockOutRule::Guideline),
+            1 => Ok(LockOutRule::Partial),
+            2 => Ok(LockOutRule::Off),
+            _ => Err(CodecError::InvalidValue),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ruleset {
+    pub(crate) das_delay: u32,
+    pub(crate) arr: u32,
+    pub(crate) das_cut_delay: u32,
+    pub(crate) preserve_das: bool,
+    pub(crate) soft_drop: SoftDrop,
+    pub(crate) lock_delay: u32,
+    pub(crate) lock_resets: u32,
+    pub(crate) are: u32,
+    pub(crate) line_are: u32,
+    pub(crate) line_clear_delay: u32,
+    pub(crate) line_clear_constant: u32,
+    pub(crate) line_clear_coeff: u32,
+    pub(crate) gravity_curve: GravityCurve,
+    pub(crate) hold: bool,
+    pub(crate) initial_rotation: bool,
+    pub(crate) initial_hold: bool,
+    pub(crate) hard_drop: bool,
+    pub(crate) ghost: bool,
+    pub(crate) previews: usize,
+    pub(crate) lock_out: LockOutRule,
+    pub(crate) scoring: ScoreTable,
+    pub(crate) mode: GameMode,
+    pub(crate) garbage: GarbageHoles,
+}
+
+impl Ruleset {
+    pub fn builder() -> RulesetBuilder {
+        RulesetBuilder::new()
+    }
+
+    pub fn guideline() -> Self {
+        Ruleset {
+            // 300 ms (18 ticks / 60 fps = 3/10 s)
+            das_delay: 18,
+            // 1 Tile / 2 Tick
+            arr: 2,
+            das_cut_delay: 0,
+            preserve_das: true,
+            // 20x Normal Drop Speed
+            soft_drop: SoftDrop::Multiplier(20.0),
+            // 1 s (60 ticks / 60 fps = 1 s)
+            lock_delay: 60,
+            // 25 Moves to reset lock delay
+            lock_resets: 25,
+            // The next piece comes straight away and cleared rows go on the next tick
+            are: 0,
+            line_are: 0,
+            line_clear_delay: 1,
+            // 10 / 0 for fixed and 5 / 5 for variable
+            line_clear_constant: 10,
+            line_clear_coeff: 0,
+            gravity_curve: GravityCurve::Guideline,
+            hold: true,
+            initial_rotation: true,
+            initial_hold: true,
+            hard_drop: true,
+            ghost: true,
+            previews: 6,
+            lock_out: LockOutRule::Guideline,
+            scoring: ScoreTable::guideline(),
+            mode: GameMode::endless(),
+            garbage: GarbageHoles::Clean,
+        }
+    }
+
+    // NES rules, meant to be played with the Nintendo rotation system
+    pub fn classic() -> Self {
+        Ruleset {
+            // 16 ticks before auto shift, then 1 tile every 6 ticks
+            das_delay: 16,
+            arr: 6,
+            das_cut_delay: 0,
+            preserve_das: true,
+            // 1 row every 2 ticks
+            soft_drop: SoftDrop::Fixed(0.5),
+            // No lock delay to speak of and nothing resets it
+            lock_delay: 1,
+            lock_resets: 0,
+            // 10 frames before the next piece, 18 more while the rows flash
+            are: 10,
+            line_are: 10,
+            line_clear_delay: 18,
+            line_clear_constant: 10,
+            line_clear_coeff: 0,
+            gravity_curve: GravityCurve::Nes,
+            // Only the next piece is shown and there is no hold, hard drop or ghost
+            hold: false,
+            initial_rotation: false,
+            initial_hold: false,
+            hard_drop: false,
+            ghost: false,
+            previews: 1,
+            // The game only ends when a piece can't spawn
+            lock_out: LockOutRule::Off,
+            scoring: ScoreTable::classic(),
+            mode: GameMode::endless(),
+            garbage: GarbageHoles::Clean,
+        }
+    }
+
+    pub fn tgm() -> Self {
+        Ruleset {
+            // 14 ticks before auto shift, then 1 tile every tick
+            das_delay: 14,
+            arr: 1,
+            das_cut_delay: 0,
+            // DAS charges during ARE
+            preserve_das: true,
+            // 1G
+            soft_drop: SoftDrop::Fixed(1.0),
+            // 30 ticks, only reset by the piece stepping down
+            lock_delay: 30,
+            lock_resets: 0,
+            // TGM1 timings
+            are: 30,
+            line_are: 30,
+            line_clear_delay: 41,
+            line_clear_constant: 10,
+            line_clear_coeff: 0,
+            gravity_curve: GravityCurve::Tgm,
+            // One next piece, and no hold, hard drop or ghost
+            hold: false,
+            initial_rotation: true,
+            initial_hold: false,
+            hard_drop: false,
+            ghost: false,
+            previews: 1,
+            lock_out: LockOutRule::Off,
+            scoring: ScoreTable::guideline(),
+            mode: GameMode::endless(),
+            garbage: GarbageHoles::Clean,
+        }
+    }
+
+    // Forgiving handling for people walking up to the wall display
+    pub fn casual() -> Self {
+        Ruleset {
+            // 400 ms before auto shift, then 1 tile every 3 ticks
+            das_delay: 24,
+            arr: 3,
+            das_cut_delay: 0,
+            // DAS charges again for every piece
+            preserve_das: false,
+            soft_drop: SoftDrop::Multiplier(10.0),
+            // 1.5 s
+            lock_delay: 90,
+            lock_resets: 30,
+            // Leave cleared rows up long enough to see on the wall
+            are: 6,
+            line_are: 6,
+            line_clear_delay: 24,
+            line_clear_constant: 15,
+            line_clear_coeff: 0,
+            gravity_curve: GravityCurve::Guideline,
+            hold: true,
+            initial_rotation: true,
+            initial_hold: true,
+            hard_drop: true,
+            ghost: true,
+            previews: 6,
+            lock_out: LockOutRule::Guideline,
+            scoring: ScoreTable::guideline(),
+            mode: GameMode::endless(),
+            garbage: GarbageHoles::Clean,
+        }
+    }
+
+    pub fn das_delay(&self) -> u32 {
+        self.das_delay
+    }
+
+    // Ticks between auto shifts once DAS is charged, 0 shifts to the wall at once
+    pub fn arr(&self) -> u32 {
+        self.arr
+    }
+
+    // Ticks auto shift waits after a rotation, hold or spawn (DCD)
+    pub fn das_cut_delay(&self) -> u32 {
+        self.das_cut_delay
+    }
+
+    // Keep DAS charged across pieces and charge it during ARE
+    pub fn preserve_das(&self) -> bool {
+        self.preserve_das
+    }
+
+    pub fn soft_drop(&self) -> SoftDrop {
+        self.soft_drop
+    }
+
+    pub fn lock_delay(&self) -> u32 {
+        self.lock_delay
+    }
+
+    pub fn lock_resets(&self) -> u32 {
+        self.lock_resets
+    }
+
+    // Ticks between a piece locking and the next one spawning
+    pub fn are(&self) -> u32 {
+        self.are
+    }
+
+    // Used instead of `are` after a line clear, counted once the rows are gone
+    pub fn line_are(&self) -> u32 {
+        self.line_are
+    }
+
+    // Ticks cleared rows stay on the board
+    pub fn line_clear_delay(&self) -> u32 {
+        self.line_clear_delay
+    }
+
+    pub fn line_clear_constant(&self) -> u32 {
+        self.line_clear_constant
+    }
+
+    pub fn line_clear_coeff(&self) -> u32 {
+        self.line_clear_coeff
+    }
+
+    pub fn gravity_curve(&self) -> GravityCurve {
+        self.gravity_curve
+    }
+
+    pub fn hold(&self) -> bool {
+        self.hold
+    }
+
+    // Rotating during ARE turns the next piece as it spawns
+    pub fn initial_rotation(&self) -> bool {
+        self.initial_rotation
+    }
+
+    // Holding during ARE swaps the next piece before it spawns
+    pub fn initial_hold(&self) -> bool {
+        self.initial_hold
+    }
+
+    pub fn hard_drop(&self) -> bool {
+        self.hard_drop
+    }
+
+    pub fn ghost(&self) -> bool {
+        self.ghost
+    }
+
+    pub fn previews(&self) -> usize {
+        self.previews
+    }
+
+    pub fn lock_out(&self) -> LockOutRule {
+        self.lock_out
+    }
+
+    pub fn scoring(&self) -> &ScoreTable {
+        &self.scoring
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn garbage(&self) -> GarbageHoles {
+        self.garbage
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.u32(self.das_delay)?;
+        w.u32(self.arr)?;
+        w.u32(self.das_cut_delay)?;
+        w.bool(self.preserve_das)?;
+        self.soft_drop.encode(w)?;
+        w.u32(self.lock_delay)?;
+        w.u32(self.lock_resets)?;
+        w.u32(self.are)?;
+        w.u32(self.line_are)?;
+        w.u32(self.line_clear_delay)?;
+        w.u32(self.line_clear_constant)?;
+        w.u32(self.line_clear_coeff)?;
+        self.gravity_curve.encode(w)?;
+        w.bool(self.hold)?;
+        w.bool(self.initial_rotation)?;
+        w.bool(self.initial_hold)?;
+        w.bool(self.hard_drop)?;
+        w.bool(self.ghost)?;
+        w.u8(self.previews as u8)?;
+        self.lock_out.encode(w)?;
+        self.scoring.encode(w)?;
+        self.mode.encode(w)?;
+        self.garbage.encode(w)
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let ruleset = Ruleset {
+            das_delay: r.u32()?,
+            arr: r.u32()?,
+            das_cut_delay: r.u32()?,
+            preserve_das: r.bool()?,
+            soft_drop: SoftDrop::decode(r)?,
+            lock_delay: r.u32()?,
+            lock_resets: r.u32()?,
+            are: r.u32()?,
+            line_are: r.u32()?,
+            line_clear_delay: r.u32()?,
+            line_clear_constant: r.u32()?,
+            line_clear_coeff: r.u32()?,
+            gravity_curve: GravityCurve::decode(r)?,
+            hold: r.bool()?,
+            initial_rotation: r.bool()?,
+            initial_hold: r.bool()?,
+            hard_drop: r.bool()?,
+            ghost: r.bool()?,
+            previews: r.u8()? as usize,
+            lock_out: LockOutRule::decode(r)?,
+            scoring: ScoreTable::decode(r)?,
+            mode: GameMode::decode(r)?,
+            garbage: GarbageHoles::decode(r)?,
+        };
+
+        // Decoded rulesets get the same checks as built ones
+        RulesetBuilder::from(ruleset)
+            .build()
+            .map_err(|_| CodecError::InvalidValue)
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset::guideline()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulesetError {
+    InvalidSoftDrop,
+    ZeroLockDelay,
+    ZeroLevelGoal,
+    ZeroModeGoal,
+    TooMuchGarbage,
+    InvalidGarbageChance,
+    TooManyPreviews,
+}
+
+impl fmt::Display for RulesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesetError::InvalidSoftDrop => write!(f, "soft drop must be a multiplier of at least 1 or a positive number of rows per tick"),
+            RulesetError::ZeroLockDelay => write!(f, "lock delay must be at least 1 tick"),
+            RulesetError::ZeroLevelGoal => write!(f, "the first level must need at least 1 line"),
+            RulesetError::ZeroModeGoal => write!(f, "the game mode goal must be at least 1"),
+            RulesetError::TooMuchGarbage => write!(f, "dig mode can start with at most 20 rows of garbage"),
+            RulesetError::InvalidGarbageChance => write!(f, "garbage hole change chance must be a percentage"),
+            RulesetError::TooManyPreviews => write!(f, "at most {} next pieces can be shown", MAX_PREVIEWS),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RulesetBuilder {
+    ruleset: Ruleset,
+}
+
+impl RulesetBuilder {
+    pub fn new() -> Self {
+        RulesetBuilder {
+            ruleset: Ruleset::default(),
+        }
+    }
+
+    pub fn das_delay(mut self, ticks: u32) -> Self {
+        self.ruleset.das_delay = ticks;
+        self
+    }
+
+    pub fn arr(mut self, ticks: u32) -> Self {
+        self.ruleset.arr = ticks;
+        self
+    }
+
+    pub fn das_cut_delay(mut self, ticks: u32) -> Self {
+        self.ruleset.das_cut_delay = ticks;
+        self
+    }
+
+    pub fn preserve_das(mut self, enabled: bool) -> Self {
+        self.ruleset.preserve_das = enabled;
+        self
+    }
+
+    pub fn soft_drop(mut self, soft_drop: SoftDrop) -> Self {
+        self.ruleset.soft_drop = soft_drop;
+        self
+    }
+
+    pub fn lock_delay(mut self, ticks: u32) -> Self {
+        self.ruleset.lock_delay = ticks;
+        self
+    }
+
+    pub fn lock_resets(mut self, resets: u32) -> Self {
+        self.ruleset.lock_resets = resets;
+        self
+    }
+
+    pub fn are(mut self, ticks: u32) -> Self {
+        self.ruleset.are = ticks;
+        self
+    }
+
+    pub fn line_are(mut self, ticks: u32) -> Self {
+        self.ruleset.line_are = ticks;
+        self
+    }
+
+    pub fn line_clear_delay(mut self, ticks: u32) -> Self {
+        self.ruleset.line_clear_delay = ticks;
+        self
+    }
+
+    // Lines needed per level are constant + level * coeff
+    pub fn level_goal(mut self, constant: u32, coeff: u32) -> Self {
+        self.ruleset.line_clear_constant = constant;
+        self.ruleset.line_clear_coeff = coeff;
+        self
+    }
+
+    pub fn gravity_curve(mut self, curve: GravityCurve) -> Self {
+        self.ruleset.gravity_curve = curve;
+        self
+    }
+
+    pub fn hold(mut self, enabled: bool) -> Self {
+        self.ruleset.hold = enabled;
+        self
+    }
+
+    pub fn initial_rotation(mut self, enabled: bool) -> Self {
+        self.ruleset.initial_rotation = enabled;
+        self
+    }
+
+    pub fn initial_hold(mut self, enabled: bool) -> Self {
+        self.ruleset.initial_hold = enabled;
+        self
+    }
+
+    pub fn hard_drop(mut self, enabled: bool) -> Self {
+        self.ruleset.hard_drop = enabled;
+        self
+    }
+
+    pub fn ghost(mut self, enabled: bool) -> Self {
+        self.ruleset.ghost = enabled;
+        self
+    }
+
+    pub fn previews(mut self, count: usize) -> Self {
+        self.ruleset.previews = count;
+        self
+    }
+
+    pub fn lock_out(mut self, rule: LockOutRule) -> Self {
+        self.ruleset.lock_out = rule;
+        self
+    }
+
+    pub fn scoring(mut self, scoring: ScoreTable) -> Self {
+        self.ruleset.scoring = scoring;
+        self
+    }
+
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.ruleset.mode = mode;
+        self
+    }
+
+    pub fn garbage(mut self, holes: GarbageHoles) -> Self {
+        self.ruleset.garbage = holes;
+        self
+    }
+
+    pub fn build(self) -> Result<Ruleset, RulesetError> {
+        let r = &self.ruleset;
+        let soft_drop_valid = match r.soft_drop {
+            SoftDrop::Multiplier(m) => m.is_finite() && m >= 1.0,
+            SoftDrop::Fixed(rows) => rows.is_finite() && rows > 0.0,
+            SoftDrop::Instant => true,
+        };
+        if !soft_drop_valid {
+            return Err(RulesetError::InvalidSoftDrop);
+        }
+
+        if r.lock_delay == 0 {
+            return Err(RulesetError::ZeroLockDelay);
+        }
+
+        if r.line_clear_constant == 0 {
+            return Err(RulesetError::ZeroLevelGoal);
+        }
+
+        let goals = [r.mode.line_goal(), r.mode.time_limit(), r.mode.garbage_goal()];
+        if goals.contains(&Some(0)) {
+            return Err(RulesetError::ZeroModeGoal);
+        }
+
+        if r.mode.garbage_goal().is_some_and(|rows| rows > 20) {
+            return Err(RulesetError::TooMuchGarbage);
+        }
+
+        if r.previews > MAX_PREVIEWS {
+            return Err(RulesetError::TooManyPreviews);
+        }
+
+        if let GarbageHoles::Changes { percent } = r.garbage {
+            if percent > 100 {
+                return Err(RulesetError::InvalidGarbageChance);
+            }
+        }
+
+        Ok(self.ruleset)
+    }
+}
+
+impl Default for RulesetBuilder {
+    fn default() -> Self {
+        RulesetBuilder::new()
+    }
+}
+
+impl From<Ruleset> for RulesetBuilder {
+    fn from(ruleset: Ruleset) -> Self {
+        RulesetBuilder { ruleset }
+    }
+}
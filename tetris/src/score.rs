@@ -0,0 +1,136 @@
+use crate::codec::{CodecError, Reader, Writer};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpinKind {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClearKind {
+    pub lines: u32,
+    pub spin: SpinKind,
+    // Number of clears in a row before this one, 0 for the first clear
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+}
+
+impl ClearKind {
+    // Tetrises and spins that clear lines keep the back to back chain going
+    pub fn difficult(&self) -> bool {
+        self.lines >= 4 || (self.spin != SpinKind::None && self.lines > 0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoreTable {
+    // Indexed by lines cleared
+    pub lines: [u32; 5],
+    pub mini: [u32; 3],
+    pub t_spin: [u32; 4],
+    pub perfect_clear: [u32; 5],
+    pub back_to_back_perfect_clear: u32,
+    pub combo: u32,
+    pub soft_drop: u32,
+    pub hard_drop: u32,
+    // Difficult clears are worth 3/2 when back to back
+    pub back_to_back: bool,
+}
+
+impl ScoreTable {
+    pub fn guideline() -> Self {
+        ScoreTable {
+            lines: [0, 100, 300, 500, 800],
+            mini: [100, 200, 400],
+            t_spin: [400, 800, 1200, 1600],
+            perfect_clear: [0, 800, 1200, 1800, 2000],
+            back_to_back_perfect_clear: 3200,
+            combo: 50,
+            soft_drop: 1,
+            hard_drop: 2,
+            back_to_back: true,
+        }
+    }
+
+    // NES values, spins count as plain clears
+    pub fn classic() -> Self {
+        ScoreTable {
+            lines: [0, 40, 100, 300, 1200],
+            mini: [0, 40, 100],
+            t_spin: [0, 40, 100, 300],
+            perfect_clear: [0; 5],
+            back_to_back_perfect_clear: 0,
+            combo: 0,
+            soft_drop: 1,
+            hard_drop: 0,
+            back_to_back: false,
+        }
+    }
+
+    // Points for a lock before the level multiplier is applied
+    pub fn clear_points(&self, clear: &ClearKind) -> u32 {
+        let lines = clear.lines as usize;
+        let mut points = match clear.spin {
+            SpinKind::None => self.lines[lines.min(4)],
+            SpinKind::Mini => self.mini[lines.min(2)],
+            SpinKind::Full => self.t_spin[lines.min(3)],
+        };
+
+        if self.back_to_back && clear.back_to_back {
+            points += points / 2;
+        }
+
+        if clear.lines > 0 {
+            points += self.combo * clear.combo;
+        }
+
+        if clear.perfect_clear {
+            points += if clear.back_to_back && clear.lines >= 4 {
+                self.back_to_back_perfect_clear
+            } else {
+                self.perfect_clear[lines.min(4)]
+            };
+        }
+
+        points
+    }
+
+    pub fn encode(&self, w: &mut Writer) -> Result<(), CodecError> {
+        for v in self.lines.iter().chain(&self.mini).chain(&self.t_spin).chain(&self.perfect_clear) {
+            w.u32(*v)?;
+        }
+        w.u32(self.back_to_back_perfect_clear)?;
+        w.u32(self.combo)?;
+        w.u32(self.soft_drop)?;
+        w.u32(self.hard_drop)?;
+        w.bool(self.back_to_back)
+    }
+
+    pub fn decode(r: &mut Reader) -> Result<Self, CodecError> {
+        let mut table = ScoreTable::guideline();
+        for v in table
+            .lines
+            .iter_mut()
+            .chain(&mut table.mini)
+            .chain(&mut table.t_spin)
+            .chain(&mut table.perfect_clear)
+        {
+            *v = r.u32()?;
+        }
+        table.back_to_back_perfect_clear = r.u32()?;
+        table.combo = r.u32()?;
+        table.soft_drop = r.u32()?;
+        table.hard_drop = r.u32()?;
+        table.back_to_back = r.bool()?;
+        Ok(table)
+    }
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        ScoreTable::guideline()
+    }
+}
@@ -0,0 +1,437 @@
+use crate::codec::{CodecError, Reader, Writer};
+use crate::garbage::{GarbageGenerator, GARBAGE_COLOR};
+use crate::input::InputFrame;
+use crate::mode::GameResult;
+use crate::random::StatefulRandomizer;
+use crate::rotate::Rotate;
+use crate::ruleset::Ruleset;
+use crate::score::{ClearKind, SpinKind};
+use crate::{CurrentPiece, Game, HeldDirection, Piece, Rotation, ShapeTable};
+
+const MAGIC: [u8; 4] = *b"LTSS";
+const VERSION: u8 = 14;
+
+pub const SNAPSHOT_SIZE: usize = 768;
+
+// Playfield cells are stored as a nibble each: 0 for empty, the piece index + 1 or garbage
+const EMPTY_CELL: u8 = 0;
+const GARBAGE_CELL: u8 = 8;
+
+#[derive(Clone)]
+pub struct Snapshot {
+    data: [u8; SNAPSHOT_SIZE],
+    len: usize,
+}
+
+impl Snapshot {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, CodecError> {
+        if bytes.len() > SNAPSHOT_SIZE {
+            return Err(CodecError::BufferTooSmall);
+        }
+
+        let mut r = Reader::new(bytes);
+        read_header(&mut r)?;
+
+        let mut data = [0; SNAPSHOT_SIZE];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Ok(Snapshot {
+            data,
+            len: bytes.len(),
+        })
+    }
+}
+
+impl core::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Snapshot").field("len", &self.len).finish()
+    }
+}
+
+fn read_header(r: &mut Reader) -> Result<(), CodecError> {
+    if r.bytes::<4>()? != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+
+    let version = r.u8()?;
+    if version != VERSION {
+        return Err(CodecError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+fn write_piece(w: &mut Writer, piece: Option<Piece>) -> Result<(), CodecError> {
+    w.u8(piece.map_or(u8::MAX, |p| p as u8))
+}
+
+fn read_piece(r: &mut Reader) -> Result<Option<Piece>, CodecError> {
+    match r.u8()? {
+        u8::MAX => Ok(None),
+        i => Piece::from_index(i).map(Some).ok_or(CodecError::InvalidValue),
+    }
+}
+
+fn write_option_u32(w: &mut Writer, v: Option<u32>) -> Result<(), CodecError> {
+    w.bool(v.is_some())?;
+    w.u32(v.unwrap_or(0))
+}
+
+fn read_option_u32(r: &mut Reader) -> Result<Option<u32>, CodecError> {
+    let some = r.bool()?;
+    let v = r.u32()?;
+    Ok(if some { Some(v) } else { None })
+}
+
+fn write_current_piece(w: &mut Writer, piece: &CurrentPiece) -> Result<(), CodecError> {
+    w.u8(piece.piece as u8)?;
+    w.u8(piece.x as u8)?;
+    w.u8(piece.y as u8)?;
+    w.u8(piece.rotation as u8)
+}
+
+fn read_current_piece(
+    r: &mut Reader,
+    shapes: &'static ShapeTable,
+) -> Result<CurrentPiece, CodecError> {
+    let piece = Piece::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+    let x = r.u8()? as u32;
+    let y = r.u8()? as u32;
+    // Anything further out is past the walls or the top of the playfield
+    if x > 10 || y >= 36 {
+        return Err(CodecError::InvalidValue);
+    }
+    let rotation = Rotation::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+    Ok(CurrentPiece::with_shapes(piece, x, y, rotation, shapes))
+}
+
+fn spin_index(spin: SpinKind) -> u8 {
+    match spin {
+        SpinKind::None => 0,
+        SpinKind::Mini => 1,
+        SpinKind::Full => 2,
+    }
+}
+
+fn read_spin(r: &mut Reader) -> Result<SpinKind, CodecError> {
+    match r.u8()? {
+        0 => Ok(SpinKind::None),
+        1 => Ok(SpinKind::Mini),
+        2 => Ok(SpinKind::Full),
+        _ => Err(CodecError::InvalidValue),
+    }
+}
+
+fn cell_code(color: (u8, u8, u8)) -> Result<u8, CodecError> {
+    if color == (0, 0, 0) {
+        return Ok(EMPTY_CELL);
+    }
+
+    if color == GARBAGE_COLOR {
+        return Ok(GARBAGE_CELL);
+    }
+
+    Piece::ALL
+        .iter()
+        .position(|p| p.color() == color)
+        .map(|i| i as u8 + 1)
+        .ok_or(CodecError::InvalidValue)
+}
+
+fn cell_color(code: u8) -> Result<Option<(u8, u8, u8)>, CodecError> {
+    if code == EMPTY_CELL {
+        return Ok(None);
+    }
+
+    if code == GARBAGE_CELL {
+        return Ok(Some(GARBAGE_COLOR));
+    }
+
+    Piece::from_index(code - 1)
+        .map(|p| Some(p.color()))
+        .ok_or(CodecError::InvalidValue)
+}
+
+impl<RNG, ROT> Game<RNG, ROT>
+where
+    RNG: StatefulRandomizer + Clone,
+    ROT: Rotate + Clone,
+{
+    pub fn snapshot(&self) -> Result<Snapshot, CodecError> {
+        let mut data = [0; SNAPSHOT_SIZE];
+        let mut w = Writer::new(&mut data);
+        self.write_state(&mut w)?;
+        let len = w.position();
+        Ok(Snapshot { data, len })
+    }
+
+    // Leaves the game untouched if the snapshot can't be read
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), CodecError> {
+        let mut restored = self.clone();
+        restored.read_state(&mut Reader::new(snapshot.as_bytes()))?;
+        *self = restored;
+        Ok(())
+    }
+
+    fn write_state(&self, w: &mut Writer) -> Result<(), CodecError> {
+        w.bytes(&MAGIC)?;
+        w.u8(VERSION)?;
+        self.ruleset.encode(w)?;
+        self.randomizer.save_state(w)?;
+
+        w.bool(self.game_ended)?;
+        w.bool(self.paused)?;
+        write_current_piece(w, &self.current_piece)?;
+        for p in self.next_pieces {
+            write_piece(w, Some(p))?;
+        }
+        write_piece(w, self.held_piece)?;
+        w.bool(self.hold_lock)?;
+
+        for row in self.playfield_colors.iter() {
+            for pair in row.chunks(2) {
+                w.u8(cell_code(pair[0])? | (cell_code(pair[1])? << 4))?;
+            }
+        }
+
+        w.u32(self.level)?;
+        w.u32(self.line_clear_total)?;
+        w.u32(self.line_clear_count)?;
+        w.f32(self.movement)?;
+        w.u32(self.lock_ticks)?;
+        w.u32(self.lock_tries)?;
+        w.u32(self.das_ticks)?;
+        w.u32(self.arr_ticks)?;
+        w.u32(self.das_cut_ticks)?;
+        w.bool(self.left_held)?;
+        w.bool(self.right_held)?;
+        w.u8(self.held_direction as u8)?;
+        w.bool(self.drop_held)?;
+        w.u8(self.input.bits())?;
+
+        w.u8(self.line_clears.1 as u8)?;
+        for row in self.line_clears.0 {
+            w.u8(row as u8)?;
+        }
+        w.u32(self.line_clear_ticks)?;
+        write_option_u32(w, self.spawn_ticks)?;
+        w.u8(self.buffered_rotation as u8)?;
+        w.bool(self.buffered_hold)?;
+
+        w.u32(self.score)?;
+        write_option_u32(w, self.combo)?;
+        w.bool(self.back_to_back)?;
+        w.bool(self.last_clear.is_some())?;
+        let clear = self.last_clear.unwrap_or(ClearKind {
+            lines: 0,
+            spin: SpinKind::None,
+            combo: 0,
+            back_to_back: false,
+            perfect_clear: false,
+        });
+        w.u8(clear.lines as u8)?;
+        w.u8(spin_index(clear.spin))?;
+        w.u32(clear.combo)?;
+        w.bool(clear.back_to_back)?;
+        w.bool(clear.perfect_clear)?;
+        w.u8(self.last_rotation.map_or(0, |(turn, _)| turn as u8))?;
+        write_option_u32(w, self.last_rotation.map(|(_, kick)| kick as u32))?;
+
+        w.u32(self.ticks)?;
+        w.u32(self.pieces_placed)?;
+        w.bool(self.result.is_some())?;
+        if let Some(result) = &self.result {
+            result.encode(w)?;
+        }
+
+        self.garbage.encode(w)?;
+        w.u32(self.garbage_cleared)?;
+
+        w.bool(self.seed.is_some())?;
+        w.u64(self.seed.unwrap_or(0))
+    }
+
+    fn read_state(&mut self, r: &mut Reader) -> Result<(), CodecError> {
+        read_header(r)?;
+        self.ruleset = Ruleset::decode(r)?;
+        self.randomizer.load_state(r)?;
+
+        self.game_ended = r.bool()?;
+        self.paused = r.bool()?;
+        self.current_piece = read_current_piece(r, self.rotation.shapes())?;
+        for p in self.next_pieces.iter_mut() {
+            *p = read_piece(r)?.ok_or(CodecError::InvalidValue)?;
+        }
+        self.held_piece = read_piece(r)?;
+        self.hold_lock = r.bool()?;
+
+        for (y, row) in self.playfield_colors.iter_mut().enumerate() {
+            self.playfield_mask[y] = 0b1111110000000000;
+            for x in (0..10).step_by(2) {
+                let codes = r.u8()?;
+                for (i, code) in [codes & 0xF, codes >> 4].into_iter().enumerate() {
+                    row[x + i] = match cell_color(code)? {
+                        Some(c) => {
+                            self.playfield_mask[y] |= 1 << (x + i);
+                            c
+                        }
+                        None => (0, 0, 0),
+                    };
+                }
+            }
+        }
+
+        self.level = r.u32()?;
+        self.line_clear_total = r.u32()?;
+        self.line_clear_count = r.u32()?;
+        self.movement = r.f32()?;
+        self.lock_ticks = r.u32()?;
+        self.lock_tries = r.u32()?;
+        self.das_ticks = r.u32()?;
+        self.arr_ticks = r.u32()?;
+        self.das_cut_ticks = r.u32()?;
+        self.left_held = r.bool()?;
+        self.right_held = r.bool()?;
+        self.held_direction = match r.u8()? {
+            0 => HeldDirection::None,
+            1 => HeldDirection::Left,
+            2 => HeldDirection::Right,
+            _ => return Err(CodecError::InvalidValue),
+        };
+        self.drop_held = r.bool()?;
+        self.input = InputFrame::from_bits(r.u8()?);
+
+        self.line_clears.1 = r.u8()? as usize;
+        if self.line_clears.1 > 4 {
+            return Err(CodecError::InvalidValue);
+        }
+        for row in self.line_clears.0.iter_mut() {
+            *row = r.u8()? as u32;
+            if *row >= 40 {
+                return Err(CodecError::InvalidValue);
+            }
+        }
+        self.line_clear_ticks = r.u32()?;
+        self.spawn_ticks = read_option_u32(r)?;
+        // Rows are only pending between a lock and the next spawn, another lock would add more
+        if self.line_clears.1 > 0 && self.piece_active() && !self.game_ended {
+            return Err(CodecError::InvalidValue);
+        }
+        self.buffered_rotation = Rotation::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+        self.buffered_hold = r.bool()?;
+
+        self.score = r.u32()?;
+        self.combo = read_option_u32(r)?;
+        self.back_to_back = r.bool()?;
+        let has_clear = r.bool()?;
+        let clear = ClearKind {
+            lines: r.u8()? as u32,
+            spin: read_spin(r)?,
+            combo: r.u32()?,
+            back_to_back: r.bool()?,
+            perfect_clear: r.bool()?,
+        };
+        self.last_clear = if has_clear { Some(clear) } else { None };
+        let turn = Rotation::from_index(r.u8()?).ok_or(CodecError::InvalidValue)?;
+        self.last_rotation = read_option_u32(r)?.map(|kick| (turn, kick as usize));
+
+        self.ticks = r.u32()?;
+        self.pieces_placed = r.u32()?;
+        self.result = if r.bool()? {
+            Some(GameResult::decode(r)?)
+        } else {
+            None
+        };
+
+        self.garbage = GarbageGenerator::decode(r)?;
+        self.garbage_cleared = r.u32()?;
+
+        let has_seed = r.bool()?;
+        let seed = r.u64()?;
+        self.seed = if has_seed { Some(seed) } else { None };
+
+        if self.current_piece.collides(&self.playfield_mask) && !self.game_ended && self.piece_active() {
+            return Err(CodecError::InvalidValue);
+        }
+
+        self.events.clear();
+        self.update_gravity();
+        self.update_ghost();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{RandomGenerator, SeededRng};
+    use crate::rotate::SuperRotationSystem;
+
+    #[test]
+    fn pending_rows_need_a_delay() {
+        let mut game = Game::<RandomGenerator<SeededRng>, _>::from_seed(3, SuperRotationSystem::default(), Ruleset::guideline());
+        game.line_clears = ([0, 0, 0, 0], 1);
+
+        // With a piece in play
+        let snapshot = game.snapshot().unwrap();
+        assert_eq!(game.clone().restore(&snapshot), Err(CodecError::InvalidValue));
+
+        // Waiting for the rows to collapse
+        game.spawn_ticks = Some(0);
+        game.line_clear_ticks = 1;
+        let snapshot = game.snapshot().unwrap();
+        assert_eq!(game.clone().restore(&snapshot), Ok(()));
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use core::fmt;
+
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Snapshot, SNAPSHOT_SIZE};
+
+    impl Serialize for Snapshot {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.as_bytes())
+        }
+    }
+
+    struct SnapshotVisitor;
+
+    impl<'de> Visitor<'de> for SnapshotVisitor {
+        type Value = Snapshot;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {SNAPSHOT_SIZE} bytes of game snapshot")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Snapshot, E> {
+            Snapshot::from_bytes(v).map_err(E::custom)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Snapshot, A::Error> {
+            let mut data = [0; SNAPSHOT_SIZE];
+            let mut len = 0;
+            while let Some(b) = seq.next_element::<u8>()? {
+                if len == SNAPSHOT_SIZE {
+                    return Err(de::Error::invalid_length(len + 1, &self));
+                }
+                data[len] = b;
+                len += 1;
+            }
+            Snapshot::from_bytes(&data[..len]).map_err(de::Error::custom)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Snapshot {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Snapshot, D::Error> {
+            deserializer.deserialize_bytes(SnapshotVisitor)
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use core::time::Duration;
+
+use crate::TICKS_PER_SECOND;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+// Ticks a slow frame can catch up on, anything longer is dropped instead of fast forwarding
+pub const MAX_CATCH_UP: u32 = 10;
+
+pub fn ticks(duration: Duration) -> u32 {
+    (duration.as_nanos() * TICKS_PER_SECOND as u128 / NANOS_PER_SECOND as u128) as u32
+}
+
+pub fn duration(ticks: u32) -> Duration {
+    Duration::from_nanos(ticks as u64 * NANOS_PER_SECOND / TICKS_PER_SECOND as u64)
+}
+
+// Turns real time into game ticks, so a game runs at the same speed however often the
+// frontend gets around to it
+#[derive(Debug, Default, Clone)]
+pub struct FixedTimestep {
+    // Nanoseconds times ticks per second, so no time is lost to rounding
+    accumulated: u64,
+}
+
+impl FixedTimestep {
+    pub fn new() -> Self {
+        FixedTimestep { accumulated: 0 }
+    }
+
+    // Adds the time since the last call and returns how many ticks to run
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        let elapsed = elapsed.as_nanos().min(u64::MAX as u128 / 2) as u64;
+        self.accumulated = self
+            .accumulated
+            .saturating_add(elapsed.saturating_mul(TICKS_PER_SECOND as u64));
+
+        let ticks = self.accumulated / NANOS_PER_SECOND;
+        self.accumulated %= NANOS_PER_SECOND;
+        ticks.min(MAX_CATCH_UP as u64) as u32
+    }
+
+    // How far into the next tick we are, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.accumulated as f32 / NANOS_PER_SECOND as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulated = 0;
+    }
+}
@@ -0,0 +1,377 @@
+use crate::event::{EventQueue, GameEvent};
+use crate::input::InputFrame;
+use crate::mode::Outcome;
+use crate::random::Randomizer;
+use crate::rotate::Rotate;
+use crate::score::{ClearKind, SpinKind};
+use crate::{Game, TICKS_PER_SECOND};
+
+pub const GARBAGE_QUEUE_SIZE: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct AttackTable {
+    // Indexed by lines cleared
+    pub lines: [u32; 5],
+    pub mini: [u32; 3],
+    pub t_spin: [u32; 4],
+    // Added to difficult clears that are back to back
+    pub back_to_back: u32,
+    // Indexed by combo, the last entry is used for longer combos
+    pub combo: [u32; 12],
+    // Added on top of the clear itself
+    pub perfect_clear: u32,
+}
+
+impl AttackTable {
+    pub fn guideline() -> Self {
+        AttackTable {
+            lines: [0, 0, 1, 2, 4],
+            mini: [0, 0, 1],
+            t_spin: [0, 2, 4, 6],
+            back_to_back: 1,
+            combo: [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+            perfect_clear: 10,
+        }
+    }
+
+    // Rows of garbage sent by a clear
+    pub fn attack(&self, clear: &ClearKind) -> u32 {
+        let lines = clear.lines as usize;
+        if lines == 0 {
+            return 0;
+        }
+
+        let mut attack = match clear.spin {
+            SpinKind::None => self.lines[lines.min(4)],
+            SpinKind::Mini => self.mini[lines.min(2)],
+            SpinKind::Full => self.t_spin[lines.min(3)],
+        };
+
+        if clear.back_to_back {
+            attack += self.back_to_back;
+        }
+
+        attack += self.combo[(clear.combo as usize).min(self.combo.len() - 1)];
+
+        if clear.perfect_clear {
+            attack += self.perfect_clear;
+        }
+
+        attack
+    }
+}
+
+impl Default for AttackTable {
+    fn default() -> Self {
+        AttackTable::guideline()
+    }
+}
+
+// Garbage waiting to be delivered, oldest first
+#[derive(Debug, Clone)]
+pub struct GarbageQueue {
+    // Rows and the ticks left until they can be delivered
+    entries: [(u32, u32); GARBAGE_QUEUE_SIZE],
+    len: usize,
+}
+
+impl GarbageQueue {
+    pub fn new() -> Self {
+        GarbageQueue {
+            entries: [(0, 0); GARBAGE_QUEUE_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, rows: u32, delay: u32) {
+        if rows == 0 {
+            return;
+        }
+
+        if self.len == GARBAGE_QUEUE_SIZE {
+            // Out of room, add it to the newest attack instead
+            self.entries[self.len - 1].0 += rows;
+            return;
+        }
+
+        self.entries[self.len] = (rows, delay);
+        self.len += 1;
+    }
+
+    // Cancels queued garbage with an attack, returning what is left of the attack
+    pub fn cancel(&mut self, mut attack: u32) -> u32 {
+        while attack > 0 && self.len > 0 {
+            let rows = &mut self.entries[0].0;
+            let cancelled = attack.min(*rows);
+            *rows -= cancelled;
+            attack -= cancelled;
+            if *rows == 0 {
+                self.remove_first();
+            }
+        }
+
+        attack
+    }
+
+    pub fn tick(&mut self) {
+        for (_, delay) in self.entries[..self.len].iter_mut() {
+            *delay = delay.saturating_sub(1);
+        }
+    }
+
+    // Takes the oldest attack once its delay has run out
+    pub fn take_ready(&mut self) -> Option<u32> {
+        if self.len > 0 && self.entries[0].1 == 0 {
+            let rows = self.entries[0].0;
+            self.remove_first();
+            Some(rows)
+        } else {
+            None
+        }
+    }
+
+    // Total rows waiting, including those not ready yet
+    pub fn pending(&self) -> u32 {
+        self.entries[..self.len].iter().map(|(rows, _)| rows).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn remove_first(&mut self) {
+        self.entries.copy_within(1..self.len, 0);
+        self.len -= 1;
+    }
+}
+
+impl Default for GarbageQueue {
+    fn default() -> Self {
+        GarbageQueue::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersusResult {
+    // Index of the player that is still standing
+    Winner(usize),
+    // Both players topped out or reached their goal on the same tick
+    Draw,
+}
+
+// Ordered from worst to best, a player that reaches the goal beats one still playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Standing {
+    ToppedOut,
+    Playing,
+    Completed,
+}
+
+fn standing<RNG: Randomizer, ROT: Rotate>(game: &Game<RNG, ROT>) -> Standing {
+    match game.result() {
+        None => Standing::Playing,
+        Some(result) => match result.outcome {
+            Outcome::Completed => Standing::Completed,
+            Outcome::ToppedOut(_) => Standing::ToppedOut,
+        },
+    }
+}
+
+// Two games sending garbage to each other, each player is 0 or 1
+pub struct Versus<RNG, ROT>
+where
+    RNG: Randomizer,
+    ROT: Rotate,
+{
+    games: [Game<RNG, ROT>; 2],
+    queues: [GarbageQueue; 2],
+    events: [EventQueue; 2],
+    attack_table: AttackTable,
+    garbage_delay: u32,
+    sent: [u32; 2],
+    result: Option<VersusResult>,
+}
+
+impl<RNG: Randomizer, ROT: Rotate> Versus<RNG, ROT> {
+    pub fn new(first: Game<RNG, ROT>, second: Game<RNG, ROT>) -> Self {
+        Versus::with_attack_table(first, second, AttackTable::guideline())
+    }
+
+    pub fn with_attack_table(
+        first: Game<RNG, ROT>,
+        second: Game<RNG, ROT>,
+        attack_table: AttackTable,
+    ) -> Self {
+        Versus {
+            games: [first, second],
+            queues: [GarbageQueue::new(), GarbageQueue::new()],
+            events: [EventQueue::new(), EventQueue::new()],
+            attack_table,
+            garbage_delay: TICKS_PER_SECOND / 3,
+            sent: [0, 0],
+            result: None,
+        }
+    }
+
+    // Ticks an attack waits before it can be delivered, giving time to cancel it
+    pub fn set_garbage_delay(&mut self, ticks: u32) {
+        self.garbage_delay = ticks;
+    }
+
+    // Both players get the same garbage holes
+    pub fn set_garbage_seed(&mut self, seed: u64) {
+        for game in self.games.iter_mut() {
+            game.set_garbage_seed(seed);
+        }
+    }
+
+    pub fn game(&self, player: usize) -> &Game<RNG, ROT> {
+        &self.games[player]
+    }
+
+    // The game's events are taken by `update` and passed on through `poll_event`
+    pub fn game_mut(&mut self, player: usize) -> &mut Game<RNG, ROT> {
+        &mut self.games[player]
+    }
+
+    pub fn attack_table(&self) -> &AttackTable {
+        &self.attack_table
+    }
+
+    pub fn pending_garbage(&self, player: usize) -> u32 {
+        self.queues[player].pending()
+    }
+
+    // Rows of garbage a player has sent, after cancelling
+    pub fn sent(&self, player: usize) -> u32 {
+        self.sent[player]
+    }
+
+    pub fn poll_event(&mut self, player: usize) -> Option<GameEvent> {
+        self.events[player].pop()
+    }
+
+    pub fn result(&self) -> Option<VersusResult> {
+        self.result
+    }
+
+    // Runs one tick with each player's input
+    pub fn update(&mut self, frames: &[InputFrame; 2]) {
+        if self.result.is_some() {
+            return;
+        }
+
+        for ((game, queue), frame) in self.games.iter_mut().zip(self.queues.iter_mut()).zip(frames) {
+            game.tick(frame);
+            queue.tick();
+        }
+
+        // Both attacks are worked out before either is sent, so neither player gets to
+        // cancel first or have their garbage delivered a tick sooner
+        let taken = [self.take_events(0), self.take_events(1)];
+
+        for (player, (_, attack)) in taken.into_iter().enumerate() {
+            let attack = self.queues[player].cancel(attack);
+            if attack > 0 {
+                self.sent[player] += attack;
+                self.queues[1 - player].push(attack, self.garbage_delay);
+            }
+        }
+
+        // Garbage only rises when a piece locks without clearing anything
+        for (player, (locked, _)) in taken.into_iter().enumerate() {
+            if locked {
+                while let Some(rows) = self.queues[player].take_ready() {
+                    self.games[player].push_garbage(rows);
+                }
+            }
+        }
+
+        self.result = match (standing(&self.games[0]), standing(&self.games[1])) {
+            (Standing::Playing, Standing::Playing) => None,
+            (first, second) if first == second => Some(VersusResult::Draw),
+            (first, second) if first > second => Some(VersusResult::Winner(0)),
+            _ => Some(VersusResult::Winner(1)),
+        };
+    }
+
+    // Passes the game's events on, returning whether a piece locked without a clear and
+    // the attack its clears sent
+    fn take_events(&mut self, player: usize) -> (bool, u32) {
+        let mut locked = false;
+        let mut attack = 0;
+
+        while let Some(event) = self.games[player].poll_event() {
+            match &event {
+                GameEvent::PieceLocked { .. } => locked = true,
+                GameEvent::LinesCleared { kind, .. } => {
+                    locked = false;
+                    attack += self.attack_table.attack(kind);
+                }
+                _ => {}
+            }
+            self.events[player].push(event);
+        }
+
+        (locked, attack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mode::GameMode;
+    use crate::random::{RandomGenerator, SeededRng};
+    use crate::rotate::SuperRotationSystem;
+    use crate::ruleset::Ruleset;
+
+    type Generator = RandomGenerator<SeededRng>;
+
+    fn game(mode: GameMode) -> Game<Generator, SuperRotationSystem> {
+        let ruleset = Ruleset::builder().mode(mode).build().unwrap();
+        Game::from_seed(7, SuperRotationSystem::default(), ruleset)
+    }
+
+    fn run(versus: &mut Versus<Generator, SuperRotationSystem>) -> VersusResult {
+        for _ in 0..100 {
+            versus.update(&[InputFrame::empty(); 2]);
+            if let Some(result) = versus.result() {
+                return result;
+            }
+        }
+        panic!("versus never ended");
+    }
+
+    #[test]
+    fn reaching_the_goal_wins() {
+        let ultra = GameMode::Ultra { ticks: 10 };
+
+        let mut versus = Versus::new(game(ultra), game(GameMode::endless()));
+        assert_eq!(run(&mut versus), VersusResult::Winner(0));
+
+        let mut versus = Versus::new(game(GameMode::endless()), game(ultra));
+        assert_eq!(run(&mut versus), VersusResult::Winner(1));
+
+        let mut versus = Versus::new(game(ultra), game(ultra));
+        assert_eq!(run(&mut versus), VersusResult::Draw);
+    }
+
+    #[test]
+    fn topping_out_loses() {
+        let mut versus = Versus::new(game(GameMode::endless()), game(GameMode::endless()));
+        versus.game_mut(0).push_garbage(30);
+        assert_eq!(run(&mut versus), VersusResult::Winner(1));
+
+        // Both games end before the next update, the one that topped out still loses
+        let mut versus = Versus::new(game(GameMode::Ultra { ticks: 10 }), game(GameMode::endless()));
+        for _ in 0..9 {
+            versus.update(&[InputFrame::empty(); 2]);
+        }
+        versus.game_mut(1).push_garbage(30);
+        assert_eq!(run(&mut versus), VersusResult::Winner(0));
+    }
+}